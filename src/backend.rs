@@ -0,0 +1,269 @@
+use calcard::icalendar;
+
+use crate::{eds, utils};
+
+// How long a single calendar, or a backend listing its calendars, gets to respond before
+// it is reported as failed.
+pub const CALENDAR_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+// A calendar, or a backend, that could not be fetched, along with the reason.
+pub struct Failure {
+    pub calendar: String,
+    pub error: anyhow::Error,
+}
+
+// A place the calendars are fetched from, like the Evolution Data Server.
+#[async_trait::async_trait]
+pub trait Backend: Send + Sync {
+    // Returns the name the backend is reported by when it cannot be reached.
+    fn name(&self) -> String;
+
+    // Returns all the calendars with events on the backend.
+    async fn calendars(&self) -> anyhow::Result<Vec<Box<dyn Calendar + '_>>>;
+
+    // Returns all the task lists on the backend. Not every backend has them.
+    async fn task_lists(&self) -> anyhow::Result<Vec<Box<dyn Calendar + '_>>> {
+        Ok(vec![])
+    }
+
+    // Returns the calendars on the backend, or, reports the backend as failed when it
+    // cannot list them in time, so that an offline server does not hold up the rest.
+    async fn reachable_calendars(&self) -> (Vec<Box<dyn Calendar + '_>>, Vec<Failure>) {
+        within_timeout(self.name(), self.calendars()).await
+    }
+
+    // Returns the task lists on the backend, or, reports the backend as failed.
+    async fn reachable_task_lists(&self) -> (Vec<Box<dyn Calendar + '_>>, Vec<Failure>) {
+        within_timeout(self.name(), self.task_lists()).await
+    }
+}
+
+// A calendar, or a task list, on a backend. Only reading the events is required, the
+// backends that cannot change them are treated as read-only.
+#[async_trait::async_trait]
+pub trait Calendar: Send + Sync {
+    fn uid(&self) -> &str;
+
+    fn name(&self) -> Option<&str>;
+
+    // Returns the "account/name" path of the calendar, if it belongs to an account.
+    fn path(&self) -> Option<String> {
+        None
+    }
+
+    // Returns whether the calendar should be used unless the hidden ones are asked for.
+    fn is_visible(&self) -> bool {
+        true
+    }
+
+    // Returns the addresses the user is known by on the calendar, to tell which of the
+    // attendees of an event they are.
+    fn addresses(&self) -> Vec<String> {
+        vec![]
+    }
+
+    // Returns a list of all the events on this calendar in the range that match the query.
+    async fn fetch_events(
+        &self,
+        starts: chrono::DateTime<chrono::Local>,
+        ends: chrono::DateTime<chrono::Local>,
+        query: &eds::query::Query,
+    ) -> anyhow::Result<Vec<eds::event::Event>>;
+
+    // Returns a list of events that were scheduled from start of yesterday to end of tomorrow.
+    async fn fetch_near_events(&self) -> anyhow::Result<Vec<eds::event::Event>> {
+        let (starts, ends) = utils::near_range()?;
+        self.fetch_events(starts, ends, &eds::query::Query::All)
+            .await
+    }
+
+    // Returns a list of all the tasks on this calendar.
+    async fn fetch_tasks(&self) -> anyhow::Result<Vec<eds::task::Task>> {
+        Ok(vec![])
+    }
+
+    async fn is_writable(&self) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    // Stores a new event on this calendar and returns its UID.
+    async fn create_event(&self, _event: &eds::event::NewEvent) -> anyhow::Result<String> {
+        anyhow::bail!("The calendar is read-only")
+    }
+}
+
+// The boxed calendars, as they are returned by the backends, are calendars too.
+#[async_trait::async_trait]
+impl<C: Calendar + ?Sized> Calendar for Box<C> {
+    fn uid(&self) -> &str {
+        (**self).uid()
+    }
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+
+    fn path(&self) -> Option<String> {
+        (**self).path()
+    }
+
+    fn is_visible(&self) -> bool {
+        (**self).is_visible()
+    }
+
+    fn addresses(&self) -> Vec<String> {
+        (**self).addresses()
+    }
+
+    async fn fetch_events(
+        &self,
+        starts: chrono::DateTime<chrono::Local>,
+        ends: chrono::DateTime<chrono::Local>,
+        query: &eds::query::Query,
+    ) -> anyhow::Result<Vec<eds::event::Event>> {
+        (**self).fetch_events(starts, ends, query).await
+    }
+
+    async fn fetch_near_events(&self) -> anyhow::Result<Vec<eds::event::Event>> {
+        (**self).fetch_near_events().await
+    }
+
+    async fn fetch_tasks(&self) -> anyhow::Result<Vec<eds::task::Task>> {
+        (**self).fetch_tasks().await
+    }
+
+    async fn is_writable(&self) -> anyhow::Result<bool> {
+        (**self).is_writable().await
+    }
+
+    async fn create_event(&self, event: &eds::event::NewEvent) -> anyhow::Result<String> {
+        (**self).create_event(event).await
+    }
+}
+
+// The calendars of several backends, as if they were all on one of them.
+pub struct Combined<'a> {
+    backends: Vec<Box<dyn Backend + 'a>>,
+}
+
+impl<'a> Combined<'a> {
+    pub fn new(backends: Vec<Box<dyn Backend + 'a>>) -> Self {
+        Self { backends }
+    }
+}
+
+// A backend that cannot be reached, like a server that is offline, is reported on its own,
+// so that the calendars from the rest are still used. The backends are asked at once.
+#[async_trait::async_trait]
+impl Backend for Combined<'_> {
+    fn name(&self) -> String {
+        self.backends
+            .iter()
+            .map(|backend| backend.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    async fn calendars(&self) -> anyhow::Result<Vec<Box<dyn Calendar + '_>>> {
+        Ok(reported(self.reachable_calendars().await))
+    }
+
+    async fn task_lists(&self) -> anyhow::Result<Vec<Box<dyn Calendar + '_>>> {
+        Ok(reported(self.reachable_task_lists().await))
+    }
+
+    async fn reachable_calendars(&self) -> (Vec<Box<dyn Calendar + '_>>, Vec<Failure>) {
+        combined(
+            futures_util::future::join_all(
+                self.backends
+                    .iter()
+                    .map(|backend| backend.reachable_calendars()),
+            )
+            .await,
+        )
+    }
+
+    async fn reachable_task_lists(&self) -> (Vec<Box<dyn Calendar + '_>>, Vec<Failure>) {
+        combined(
+            futures_util::future::join_all(
+                self.backends
+                    .iter()
+                    .map(|backend| backend.reachable_task_lists()),
+            )
+            .await,
+        )
+    }
+}
+
+// Runs the listing of a backend, reporting the backend as failed when it does not list
+// its calendars in time.
+async fn within_timeout<T>(
+    name: String,
+    list: impl std::future::Future<Output = anyhow::Result<Vec<T>>>,
+) -> (Vec<T>, Vec<Failure>) {
+    match with_timeout(list).await {
+        Ok(found) => (found, vec![]),
+        Err(error) => (
+            vec![],
+            vec![Failure {
+                calendar: name,
+                error,
+            }],
+        ),
+    }
+}
+
+// Runs the future, failing it once it is over the time a calendar gets to respond.
+pub async fn with_timeout<T>(
+    future: impl std::future::Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    match tokio::time::timeout(CALENDAR_TIMEOUT, future).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!(
+            "Timed out after {}",
+            humantime::format_duration(CALENDAR_TIMEOUT)
+        )),
+    }
+}
+
+// Joins what the backends listed, along with the ones that failed.
+fn combined<T>(listed: Vec<(Vec<T>, Vec<Failure>)>) -> (Vec<T>, Vec<Failure>) {
+    let (found, failures): (Vec<_>, Vec<_>) = listed.into_iter().unzip();
+    (
+        found.into_iter().flatten().collect(),
+        failures.into_iter().flatten().collect(),
+    )
+}
+
+// Reports the backends that failed, for the callers that cannot show them.
+fn reported<T>((found, failures): (Vec<T>, Vec<Failure>)) -> Vec<T> {
+    for failure in failures {
+        eprintln!("{:#}", failure.error);
+    }
+
+    found
+}
+
+// Returns the events in the range from the components that match the query, for the
+// backends that cannot run the queries themselves. The events are expanded first, and
+// the ones that are outside of the range are dropped after that.
+pub fn events_in_range(
+    vevents: Vec<icalendar::ICalendar>,
+    starts: chrono::DateTime<chrono::Local>,
+    ends: chrono::DateTime<chrono::Local>,
+    query: &eds::query::Query,
+) -> Vec<eds::event::Event> {
+    let vevents: Vec<_> = vevents
+        .into_iter()
+        .filter(|vevent| query.matches(&vevent.components[0]))
+        .collect();
+
+    eds::calendar::events_from_vevents(vevents.iter(), starts, ends)
+        .into_iter()
+        .filter(|event| match (event.starts, event.ends) {
+            (Some(event_starts), Some(event_ends)) => event_starts < ends && event_ends > starts,
+            (Some(event_starts), None) => event_starts >= starts && event_starts < ends,
+            _ => false,
+        })
+        .collect()
+}
@@ -0,0 +1,703 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use calcard::icalendar;
+
+use crate::{backend, eds, utils};
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+
+// The properties that tell where the calendars are, wherever the URL points at.
+const DISCOVERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:resourcetype/>
+    <d:displayname/>
+    <d:current-user-principal/>
+    <c:calendar-home-set/>
+    <c:supported-calendar-component-set/>
+  </d:prop>
+</d:propfind>"#;
+
+// The calendars on a CalDAV server, like Radicale, Nextcloud or Fastmail.
+pub struct CalDav {
+    url: reqwest::Url,
+    client: reqwest::Client,
+
+    username: Option<String>,
+    password: Option<String>,
+
+    // Whether a copy of the calendars is kept on the disk, so that only the changes to
+    // them are fetched.
+    sync: bool,
+}
+
+impl CalDav {
+    pub fn new(
+        url: &str,
+        username: Option<String>,
+        password: Option<String>,
+        sync: bool,
+    ) -> anyhow::Result<Self> {
+        let mut url = reqwest::Url::parse(url)
+            .with_context(|| format!("Could not parse \"{}\" as a URL", url))?;
+
+        // The credentials in the URL are used, unless they were given separately, and
+        // are kept out of the URL, which is also shown to the user.
+        let username =
+            username.or_else(|| Some(url.username().to_owned()).filter(|u| !u.is_empty()));
+        let password = password.or_else(|| url.password().map(|password| password.to_owned()));
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+
+        Ok(Self {
+            url,
+            client: utils::http_client()?,
+            username,
+            password,
+            sync,
+        })
+    }
+
+    // Sends a WebDAV request and returns the multistatus the server answered with.
+    async fn request(
+        &self,
+        method: &str,
+        url: &reqwest::Url,
+        depth: &str,
+        body: String,
+    ) -> anyhow::Result<Multistatus> {
+        let method = reqwest::Method::from_bytes(method.as_bytes())
+            .with_context(|| format!("Could not build a {} request", method))?;
+
+        let mut request = self
+            .client
+            .request(method, url.clone())
+            .header("Depth", depth)
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body);
+
+        if let Some(username) = &self.username {
+            request = request.basic_auth(username, self.password.as_ref());
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Could not reach \"{}\"", url))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("\"{}\" answered with {}", url, status);
+        }
+
+        let text = response
+            .text()
+            .await
+            .with_context(|| format!("Could not read the answer from \"{}\"", url))?;
+
+        Multistatus::parse(&text, url)
+            .with_context(|| format!("Could not parse the answer from \"{}\"", url))
+    }
+
+    // Returns the calendars found from the URL, which can point at a calendar, at the
+    // calendar home, at the principal of the user, or, at the server itself.
+    async fn discover(&self) -> anyhow::Result<Vec<Resource>> {
+        let resource = self
+            .request("PROPFIND", &self.url, "0", DISCOVERY.to_owned())
+            .await?
+            .responses
+            .into_iter()
+            .next()
+            .with_context(|| format!("\"{}\" did not answer with its properties", self.url))?;
+
+        if resource.is_calendar {
+            return Ok(vec![resource]);
+        }
+
+        let home = match (resource.home_set, resource.principal) {
+            (Some(home), _) => home,
+            (None, Some(principal)) => self
+                .request("PROPFIND", &principal, "0", DISCOVERY.to_owned())
+                .await?
+                .responses
+                .into_iter()
+                .find_map(|resource| resource.home_set)
+                .with_context(|| format!("Could not find the calendars of \"{}\"", principal))?,
+            // Otherwise, the URL is taken to be the calendar home itself.
+            (None, None) => self.url.clone(),
+        };
+
+        Ok(self
+            .request("PROPFIND", &home, "1", DISCOVERY.to_owned())
+            .await?
+            .responses
+            .into_iter()
+            .filter(|resource| resource.is_calendar)
+            .collect())
+    }
+
+    // Returns the calendars that can hold the components of the type.
+    async fn calendars_of(
+        &self,
+        component: &str,
+    ) -> anyhow::Result<Vec<Box<dyn backend::Calendar + '_>>> {
+        Ok(self
+            .discover()
+            .await
+            .with_context(|| format!("Could not list the calendars on \"{}\"", self.url))?
+            .into_iter()
+            .filter(|resource| resource.supports(component))
+            .map(|resource| Box::new(Calendar::new(self, resource)) as Box<dyn backend::Calendar>)
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl backend::Backend for CalDav {
+    fn name(&self) -> String {
+        self.url.to_string()
+    }
+
+    async fn calendars(&self) -> anyhow::Result<Vec<Box<dyn backend::Calendar + '_>>> {
+        self.calendars_of("VEVENT").await
+    }
+
+    async fn task_lists(&self) -> anyhow::Result<Vec<Box<dyn backend::Calendar + '_>>> {
+        self.calendars_of("VTODO").await
+    }
+}
+
+pub struct Calendar<'a> {
+    dav: &'a CalDav,
+
+    url: reqwest::Url,
+    uid: String,
+    name: Option<String>,
+
+    // The server the calendar is on, which tells apart the calendars with the same name.
+    account: Option<String>,
+}
+
+// The copy of a calendar kept on the disk, along with the token to fetch the changes
+// made to it since.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct Copy {
+    sync_token: Option<String>,
+    // The objects on the calendar, by their URL.
+    objects: BTreeMap<String, String>,
+}
+
+impl<'a> Calendar<'a> {
+    fn new(dav: &'a CalDav, resource: Resource) -> Self {
+        Self {
+            dav,
+            uid: resource.url.to_string(),
+            account: resource.url.host_str().map(|host| host.to_owned()),
+            url: resource.url,
+            name: resource.name,
+        }
+    }
+
+    // Returns the objects on the calendar with components of the type, in the range, if
+    // there is one. When the calendar is synced, all of its objects are returned instead.
+    async fn objects(
+        &self,
+        component: &str,
+        range: Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>,
+    ) -> anyhow::Result<Vec<String>> {
+        if self.dav.sync {
+            return self.synced_objects().await;
+        }
+
+        let time_range = match range {
+            Some((starts, ends)) => format!(
+                r#"<c:time-range start="{}" end="{}"/>"#,
+                starts.format("%Y%m%dT%H%M%SZ"),
+                ends.format("%Y%m%dT%H%M%SZ"),
+            ),
+            None => String::new(),
+        };
+
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data/>
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="{}">{}</c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#,
+            component, time_range,
+        );
+
+        Ok(self
+            .dav
+            .request("REPORT", &self.url, "1", body)
+            .await?
+            .responses
+            .into_iter()
+            .filter_map(|resource| resource.calendar_data)
+            .collect())
+    }
+
+    // Brings the copy of the calendar on the disk up to date, and returns all of its
+    // objects. An expired token only costs fetching the whole calendar again.
+    async fn synced_objects(&self) -> anyhow::Result<Vec<String>> {
+        let path = utils::cache_path("caldav", &self.uid, "json");
+
+        let mut copy: Copy = path
+            .as_ref()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+
+        let changes = match self.sync_collection(copy.sync_token.as_deref()).await {
+            Ok(changes) => changes,
+            Err(_) if copy.sync_token.is_some() => {
+                copy = Copy::default();
+                self.sync_collection(None).await?
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Some servers only list what changed, the objects are then fetched separately.
+        let mut missing = Vec::new();
+        for resource in changes.responses {
+            if resource.url == self.url {
+                continue;
+            }
+
+            if resource.removed {
+                copy.objects.remove(resource.url.as_str());
+                continue;
+            }
+
+            match resource.calendar_data {
+                Some(data) => {
+                    copy.objects.insert(resource.url.to_string(), data);
+                }
+                None => missing.push(resource.url),
+            }
+        }
+
+        if !missing.is_empty() {
+            for resource in self.multiget(&missing).await?.responses {
+                if let Some(data) = resource.calendar_data {
+                    copy.objects.insert(resource.url.to_string(), data);
+                }
+            }
+        }
+
+        copy.sync_token = changes.sync_token;
+
+        // Failing to keep the copy only makes the next fetch slower, so, it is reported
+        // without failing this one.
+        if let Some(path) = path
+            && let Err(err) = utils::write_file(&path, &serde_json::to_vec(&copy)?)
+        {
+            eprintln!("{:#}", err);
+        }
+
+        Ok(copy.objects.into_values().collect())
+    }
+
+    // Returns the changes made to the calendar since the token, or, everything on it
+    // without a token.
+    async fn sync_collection(&self, token: Option<&str>) -> anyhow::Result<Multistatus> {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<d:sync-collection xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:sync-token>{}</d:sync-token>
+  <d:sync-level>1</d:sync-level>
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data/>
+  </d:prop>
+</d:sync-collection>"#,
+            escape(token.unwrap_or_default()),
+        );
+
+        self.dav.request("REPORT", &self.url, "0", body).await
+    }
+
+    // Returns the objects at the URLs.
+    async fn multiget(&self, urls: &[reqwest::Url]) -> anyhow::Result<Multistatus> {
+        let hrefs: String = urls
+            .iter()
+            .map(|url| format!("\n  <d:href>{}</d:href>", escape(url.path())))
+            .collect();
+
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data/>
+  </d:prop>{}
+</c:calendar-multiget>"#,
+            hrefs,
+        );
+
+        self.dav.request("REPORT", &self.url, "1", body).await
+    }
+}
+
+#[async_trait::async_trait]
+impl backend::Calendar for Calendar<'_> {
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn path(&self) -> Option<String> {
+        match (&self.account, &self.name) {
+            (Some(account), Some(name)) => Some(format!("{}/{}", account, name)),
+            _ => None,
+        }
+    }
+
+    // Most of the servers sign in with the address of the user.
+    fn addresses(&self) -> Vec<String> {
+        self.dav
+            .username
+            .iter()
+            .filter(|username| username.contains('@'))
+            .cloned()
+            .collect()
+    }
+
+    // The server only picks the events in the range, the queries are run here.
+    async fn fetch_events(
+        &self,
+        starts: chrono::DateTime<chrono::Local>,
+        ends: chrono::DateTime<chrono::Local>,
+        query: &eds::query::Query,
+    ) -> anyhow::Result<Vec<eds::event::Event>> {
+        let vevents = self
+            .objects("VEVENT", Some((starts.to_utc(), ends.to_utc())))
+            .await?
+            .iter()
+            .flat_map(|object| {
+                eds::calendar::parse_components(object, &icalendar::ICalendarComponentType::VEvent)
+            })
+            .collect();
+
+        Ok(backend::events_in_range(vevents, starts, ends, query))
+    }
+
+    async fn fetch_tasks(&self) -> anyhow::Result<Vec<eds::task::Task>> {
+        let vtodos: Vec<_> = self
+            .objects("VTODO", None)
+            .await?
+            .iter()
+            .flat_map(|object| {
+                eds::calendar::parse_components(object, &icalendar::ICalendarComponentType::VTodo)
+            })
+            .collect();
+
+        Ok(eds::calendar::tasks_from_vtodos(vtodos.iter()))
+    }
+}
+
+// The answer to a WebDAV request, with a response for every resource it covers.
+struct Multistatus {
+    responses: Vec<Resource>,
+    sync_token: Option<String>,
+}
+
+// A resource in a multistatus, along with the properties that were found on it.
+struct Resource {
+    url: reqwest::Url,
+
+    // Whether the resource was removed, in the answers to the sync requests.
+    removed: bool,
+
+    is_calendar: bool,
+    name: Option<String>,
+    // The types of the components the calendar can hold. All of them, when empty.
+    components: Vec<String>,
+
+    principal: Option<reqwest::Url>,
+    home_set: Option<reqwest::Url>,
+
+    calendar_data: Option<String>,
+}
+
+impl Resource {
+    fn supports(&self, component: &str) -> bool {
+        self.components.is_empty() || self.components.iter().any(|c| c == component)
+    }
+}
+
+impl Multistatus {
+    // Parses a multistatus, resolving the URLs in it against the URL it was asked on.
+    fn parse(text: &str, base: &reqwest::Url) -> anyhow::Result<Self> {
+        let doc = roxmltree::Document::parse(text)?;
+        let root = doc.root_element();
+
+        let mut responses = Vec::new();
+        for response in children(root, DAV, "response") {
+            let Some(href) = child(response, DAV, "href").and_then(|href| href.text()) else {
+                continue;
+            };
+
+            let mut resource = Resource {
+                url: base.join(href.trim())?,
+                removed: child(response, DAV, "status")
+                    .and_then(|status| status.text())
+                    .is_some_and(|status| status.contains(" 404")),
+                is_calendar: false,
+                name: None,
+                components: Vec::new(),
+                principal: None,
+                home_set: None,
+                calendar_data: None,
+            };
+
+            for propstat in children(response, DAV, "propstat") {
+                // The properties that were not found are listed under another status.
+                let found = child(propstat, DAV, "status")
+                    .and_then(|status| status.text())
+                    .is_some_and(|status| status.contains(" 200"));
+
+                let Some(prop) = child(propstat, DAV, "prop").filter(|_| found) else {
+                    continue;
+                };
+
+                for property in prop.children().filter(|node| node.is_element()) {
+                    let tag = property.tag_name();
+                    match (tag.namespace(), tag.name()) {
+                        (Some(DAV), "resourcetype") => {
+                            resource.is_calendar = child(property, CALDAV, "calendar").is_some();
+                        }
+                        (Some(DAV), "displayname") => {
+                            resource.name = property
+                                .text()
+                                .map(|name| name.trim().to_owned())
+                                .filter(|name| !name.is_empty());
+                        }
+                        (Some(DAV), "current-user-principal") => {
+                            resource.principal = href_of(property, base);
+                        }
+                        (Some(CALDAV), "calendar-home-set") => {
+                            resource.home_set = href_of(property, base);
+                        }
+                        (Some(CALDAV), "supported-calendar-component-set") => {
+                            resource.components = children(property, CALDAV, "comp")
+                                .filter_map(|comp| comp.attribute("name"))
+                                .map(|name| name.to_owned())
+                                .collect();
+                        }
+                        (Some(CALDAV), "calendar-data") => {
+                            resource.calendar_data = property.text().map(|data| data.to_owned());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            responses.push(resource);
+        }
+
+        Ok(Self {
+            responses,
+            sync_token: child(root, DAV, "sync-token")
+                .and_then(|token| token.text())
+                .map(|token| token.trim().to_owned()),
+        })
+    }
+}
+
+fn children<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    namespace: &'static str,
+    name: &'static str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.has_tag_name((namespace, name)))
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    namespace: &'static str,
+    name: &'static str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    children(node, namespace, name).next()
+}
+
+// Returns the URL in the href of a property, like the calendar home.
+fn href_of(property: roxmltree::Node, base: &reqwest::Url) -> Option<reqwest::Url> {
+    let href = child(property, DAV, "href")?.text()?;
+    base.join(href.trim()).ok()
+}
+
+// Returns the text with the characters that have a meaning in XML escaped.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Multistatus {
+        let base = reqwest::Url::parse("https://dav.example.com/calendars/me/").unwrap();
+        Multistatus::parse(text, &base).unwrap()
+    }
+
+    #[test]
+    fn parses_the_calendars_in_the_home() {
+        let multistatus = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/calendars/me/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>work/</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>
+        <d:displayname> Work </d:displayname>
+        <c:supported-calendar-component-set><c:comp name="VEVENT"/></c:supported-calendar-component-set>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#,
+        );
+
+        assert_eq!(multistatus.responses.len(), 2);
+        assert!(!multistatus.responses[0].is_calendar);
+
+        let work = &multistatus.responses[1];
+        assert!(work.is_calendar);
+        assert_eq!(
+            work.url.as_str(),
+            "https://dav.example.com/calendars/me/work/"
+        );
+        assert_eq!(work.name.as_deref(), Some("Work"));
+        assert!(work.supports("VEVENT"));
+        assert!(!work.supports("VTODO"));
+    }
+
+    #[test]
+    fn resolves_the_relative_hrefs_of_the_properties() {
+        let multistatus = parse(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:current-user-principal><d:href>../../principals/me/</d:href></d:current-user-principal>
+        <c:calendar-home-set><d:href> https://other.example.com/home/ </d:href></c:calendar-home-set>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#,
+        );
+
+        let resource = &multistatus.responses[0];
+        assert_eq!(resource.url.as_str(), "https://dav.example.com/");
+        assert_eq!(
+            resource.principal.as_ref().map(|url| url.as_str()),
+            Some("https://dav.example.com/principals/me/")
+        );
+        assert_eq!(
+            resource.home_set.as_ref().map(|url| url.as_str()),
+            Some("https://other.example.com/home/")
+        );
+    }
+
+    #[test]
+    fn skips_the_properties_that_were_not_found() {
+        let multistatus = parse(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/calendars/me/work/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/><c:calendar/></d:resourcetype></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+    <d:propstat>
+      <d:prop>
+        <d:displayname>Missing</d:displayname>
+        <c:calendar-home-set><d:href>/elsewhere/</d:href></c:calendar-home-set>
+      </d:prop>
+      <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#,
+        );
+
+        let work = &multistatus.responses[0];
+        assert!(work.is_calendar);
+        assert!(!work.removed);
+        assert_eq!(work.name, None);
+        assert_eq!(work.home_set, None);
+    }
+
+    #[test]
+    fn parses_the_changes_since_the_sync_token() {
+        let multistatus = parse(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/calendars/me/work/standup.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:getetag>"2"</d:getetag>
+        <c:calendar-data>BEGIN:VCALENDAR&#13;
+END:VCALENDAR&#13;
+</c:calendar-data>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/calendars/me/work/review.ics</d:href>
+    <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:response>
+  <d:sync-token> https://dav.example.com/sync/42 </d:sync-token>
+</d:multistatus>"#,
+        );
+
+        assert_eq!(multistatus.responses.len(), 2);
+
+        let standup = &multistatus.responses[0];
+        assert!(!standup.removed);
+        assert_eq!(
+            standup.calendar_data.as_deref(),
+            Some("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n")
+        );
+
+        let review = &multistatus.responses[1];
+        assert!(review.removed);
+        assert_eq!(review.calendar_data, None);
+
+        assert_eq!(
+            multistatus.sync_token.as_deref(),
+            Some("https://dav.example.com/sync/42")
+        );
+    }
+
+    #[test]
+    fn escapes_the_text_put_in_the_requests() {
+        assert_eq!(escape("a<b>&c"), "a&lt;b&gt;&amp;c");
+    }
+}
@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+
+use crate::{backend, caldav, feed, local, utils};
+
+// How often the feeds are fetched again, unless it is set for the feed.
+const FEED_REFRESH: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+// The settings read from "calcal/config.toml" in the config directory. The file is
+// optional, everything falls back to the EDS without it.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // The calendars to use instead of the ones on the EDS, as "[[source]]" tables.
+    #[serde(default, rename = "source")]
+    pub sources: Vec<Source>,
+
+    // The addresses you are invited by, to find you among the attendees of the events on
+    // the calendars that do not tell them.
+    #[serde(default)]
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Source {
+    // An ".ics" file, a vdir, or a directory of vdirs. A leading "~" stands for the home.
+    pub path: Option<PathBuf>,
+
+    // The URL of a CalDAV server, of a principal on it, of a calendar home, or of a
+    // single calendar.
+    pub caldav: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // A command that prints the password, like "pass show caldav", so that it does not
+    // have to be kept in the config.
+    pub password_command: Option<String>,
+
+    // Whether to keep a copy of the CalDAV calendars on the disk, and only fetch the
+    // changes to them since the last time.
+    #[serde(default)]
+    pub sync: bool,
+
+    // The URL of an ".ics" feed, over "webcal", "webcals", "https", or "http". Unlike the
+    // other sources, the feeds are shown next to the EDS calendars.
+    pub feed: Option<String>,
+    // The name to show for the feed, instead of the one it calls itself.
+    pub name: Option<String>,
+    // How often to fetch the feed again, like "30m", the copy on the disk is used until then.
+    pub refresh: Option<String>,
+}
+
+impl Config {
+    // Reads the config file, if there is one.
+    pub fn load() -> anyhow::Result<Self> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .with_context(|| format!("Could not parse \"{}\"", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Could not read \"{}\"", path.display())),
+        }
+    }
+}
+
+// Parses a source given on the command line, which is either a path, the URL of a feed,
+// or, the URL of a CalDAV server. The URLs of the feeds either use the "webcal" scheme, or,
+// point at an ".ics" file. The credentials can be given as a part of the URL.
+impl std::str::FromStr for Source {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let is_web = value.starts_with("https://") || value.starts_with("http://");
+
+        if value.starts_with("webcal://")
+            || value.starts_with("webcals://")
+            || (is_web
+                && value
+                    .split(['?', '#'])
+                    .next()
+                    .is_some_and(|url| url.ends_with(".ics")))
+        {
+            return Ok(Self {
+                feed: Some(value.to_owned()),
+                ..Default::default()
+            });
+        }
+
+        if is_web {
+            return Ok(Self {
+                caldav: Some(value.to_owned()),
+                ..Default::default()
+            });
+        }
+
+        Ok(Self {
+            path: Some(PathBuf::from(value)),
+            ..Default::default()
+        })
+    }
+}
+
+impl Source {
+    // Returns the backend the calendars of this source are read from.
+    pub fn backend(&self) -> anyhow::Result<Box<dyn backend::Backend>> {
+        match (&self.path, &self.caldav, &self.feed) {
+            (Some(path), None, None) => Ok(Box::new(local::Local::new(utils::expand_home(path)))),
+            (None, Some(url), None) => Ok(Box::new(caldav::CalDav::new(
+                url,
+                self.username.clone(),
+                self.password()?,
+                self.sync,
+            )?)),
+            (None, None, Some(url)) => {
+                let refresh = match &self.refresh {
+                    Some(refresh) => humantime::parse_duration(refresh).with_context(|| {
+                        format!("Could not parse \"{}\" as a duration", refresh)
+                    })?,
+                    None => FEED_REFRESH,
+                };
+
+                Ok(Box::new(feed::Feed::new(url, self.name.clone(), refresh)?))
+            }
+            _ => anyhow::bail!(
+                "A source should have exactly one of \"path\", \"caldav\" or \"feed\""
+            ),
+        }
+    }
+
+    // Returns whether the source is shown next to the EDS calendars, rather than in their
+    // place.
+    pub fn is_feed(&self) -> bool {
+        self.feed.is_some() && self.path.is_none() && self.caldav.is_none()
+    }
+
+    // Returns the password, running the command that prints it, if necessary.
+    fn password(&self) -> anyhow::Result<Option<String>> {
+        let Some(command) = &self.password_command else {
+            return Ok(self.password.clone());
+        };
+
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .output()
+            .with_context(|| format!("Could not run \"{}\"", command))?;
+
+        if !output.status.success() {
+            anyhow::bail!("\"{}\" failed with {}", command, output.status);
+        }
+
+        let password = String::from_utf8(output.stdout)
+            .with_context(|| format!("Could not read the password from \"{}\"", command))?;
+
+        Ok(Some(password.trim_end_matches(['\r', '\n']).to_owned()))
+    }
+}
+
+// Returns the path of the config file, following the XDG base directory spec.
+fn config_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+
+    Some(dir.join("calcal").join("config.toml"))
+}
@@ -0,0 +1,221 @@
+use calcard::icalendar;
+
+use crate::eds::event::dt_property;
+
+// The most times an alarm goes off again. The calendar apps only set a few repetitions,
+// anything past this is a broken, or, a hostile calendar.
+const MAX_REPEAT: u32 = 100;
+
+// The moment an alarm goes off, either relative to the event, or, at a fixed time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    // Relative to the start of the event. Negative offsets are before the start.
+    Start(chrono::Duration),
+    // Relative to the end of the event.
+    End(chrono::Duration),
+    // At an absolute time, regardless of the occurrence of the event.
+    At(chrono::DateTime<chrono::Utc>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Alarm {
+    pub action: Option<icalendar::ICalendarAction>,
+
+    pub trigger: Trigger,
+
+    // The number of times the alarm goes off again after the trigger, separated
+    // by the interval.
+    pub repeat: u32,
+    pub interval: Option<chrono::Duration>,
+}
+
+impl Alarm {
+    // Parses a VALARM component. Alarms without a usable trigger are ignored.
+    pub fn from_component(component: &icalendar::ICalendarComponent) -> Option<Self> {
+        let trigger = component.property(&icalendar::ICalendarProperty::Trigger)?;
+
+        let trigger = match trigger.values.first()? {
+            icalendar::ICalendarValue::Duration(duration) => {
+                let offset = duration.to_time_delta()?;
+
+                if trigger.params.iter().any(|param| {
+                    matches!(
+                        param,
+                        icalendar::ICalendarParameter::Related(icalendar::Related::End)
+                    )
+                }) {
+                    Trigger::End(offset)
+                } else {
+                    Trigger::Start(offset)
+                }
+            }
+            icalendar::ICalendarValue::PartialDateTime(_) => Trigger::At(
+                dt_property(component, &icalendar::ICalendarProperty::Trigger)?
+                    .ok()?
+                    .to_utc(),
+            ),
+            _ => return None,
+        };
+
+        let interval = component
+            .property(&icalendar::ICalendarProperty::Duration)
+            .and_then(|entry| match entry.values.first()? {
+                icalendar::ICalendarValue::Duration(duration) => duration.to_time_delta(),
+                _ => None,
+            });
+
+        // The repetitions only make sense when both of them are present.
+        let repeat = match interval {
+            Some(_) => component
+                .property(&icalendar::ICalendarProperty::Repeat)
+                .and_then(|entry| entry.values.first()?.as_integer())
+                .and_then(|repeat| u32::try_from(repeat).ok())
+                .unwrap_or_default(),
+            None => 0,
+        };
+
+        Some(Self {
+            action: component
+                .property(&icalendar::ICalendarProperty::Action)
+                .and_then(|entry| match entry.values.first()? {
+                    icalendar::ICalendarValue::Action(action) => Some(action.clone()),
+                    _ => None,
+                }),
+
+            trigger,
+
+            repeat,
+            interval,
+        })
+    }
+
+    // Returns a display alarm that goes off at the lead time before the event starts.
+    pub fn before_start(lead: chrono::Duration) -> Self {
+        Self {
+            action: Some(icalendar::ICalendarAction::Display),
+            trigger: Trigger::Start(-lead),
+            repeat: 0,
+            interval: None,
+        }
+    }
+
+    // Returns all the times this alarm goes off for an occurrence of an event. Alarms
+    // relative to the end fall back to the start when the event has no end. The times
+    // that cannot be represented are dropped.
+    pub fn trigger_times(
+        &self,
+        starts: Option<chrono::DateTime<rrule::Tz>>,
+        ends: Option<chrono::DateTime<rrule::Tz>>,
+    ) -> Vec<chrono::DateTime<chrono::Utc>> {
+        let first = match &self.trigger {
+            Trigger::Start(offset) => {
+                starts.and_then(|starts| starts.to_utc().checked_add_signed(*offset))
+            }
+            Trigger::End(offset) => ends
+                .or(starts)
+                .and_then(|ends| ends.to_utc().checked_add_signed(*offset)),
+            Trigger::At(at) => Some(*at),
+        };
+
+        let Some(first) = first else {
+            return vec![];
+        };
+
+        let interval = self.interval.unwrap_or_default();
+        (0..=self.repeat.min(MAX_REPEAT) as i32)
+            .map_while(|repetition| first.checked_add_signed(interval.checked_mul(repetition)?))
+            .collect()
+    }
+
+    // Returns the name of the action of the alarm, as it is shown to the user.
+    pub fn action_name(&self) -> &str {
+        match &self.action {
+            Some(icalendar::ICalendarAction::Audio) => "audio",
+            Some(icalendar::ICalendarAction::Display) => "display",
+            Some(icalendar::ICalendarAction::Email) => "email",
+            Some(icalendar::ICalendarAction::Procedure) => "procedure",
+            Some(icalendar::ICalendarAction::Other(other)) => other,
+            None => "display",
+        }
+    }
+}
+
+// Returns the alarms of a component from the tree it was parsed into.
+pub fn alarms_of(tree: &icalendar::ICalendar) -> Vec<Alarm> {
+    tree.alarms_for_id(0)
+        .filter_map(Alarm::from_component)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alarm(ics: &str) -> Alarm {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:event\r\nDTSTART:20250101T100000Z\r\n\
+             BEGIN:VALARM\r\nACTION:DISPLAY\r\n{}END:VALARM\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            ics.replace('\n', "\r\n"),
+        );
+        let trees = crate::eds::calendar::parse_components(
+            &ics,
+            &icalendar::ICalendarComponentType::VEvent,
+        );
+
+        alarms_of(&trees[0]).remove(0)
+    }
+
+    fn starts() -> Option<chrono::DateTime<rrule::Tz>> {
+        Some(
+            "2025-01-01T10:00:00Z"
+                .parse::<chrono::DateTime<chrono::Utc>>()
+                .unwrap()
+                .with_timezone(&rrule::Tz::UTC),
+        )
+    }
+
+    #[test]
+    fn repeats_the_alarm_at_the_interval() {
+        let alarm = alarm("TRIGGER:-PT10M\nDURATION:PT5M\nREPEAT:2\n");
+
+        let times: Vec<_> = alarm
+            .trigger_times(starts(), None)
+            .iter()
+            .map(|at| at.format("%H:%M").to_string())
+            .collect();
+        assert_eq!(times, ["09:50", "09:55", "10:00"]);
+    }
+
+    #[test]
+    fn drops_the_triggers_that_overflow() {
+        let alarm = alarm("TRIGGER:P999999999W\n");
+        assert!(alarm.trigger_times(starts(), None).is_empty());
+    }
+
+    #[test]
+    fn drops_the_repetitions_that_overflow() {
+        let alarm = alarm("TRIGGER:-PT10M\nDURATION:P999999999W\nREPEAT:3\n");
+        assert_eq!(alarm.trigger_times(starts(), None).len(), 1);
+    }
+
+    #[test]
+    fn caps_the_repetitions() {
+        let alarm = alarm("TRIGGER:-PT10M\nDURATION:PT1M\nREPEAT:400000000\n");
+        assert_eq!(
+            alarm.trigger_times(starts(), None).len(),
+            MAX_REPEAT as usize + 1
+        );
+    }
+
+    #[test]
+    fn caps_the_repetitions_past_the_integers() {
+        let alarm = Alarm {
+            repeat: u32::MAX,
+            ..alarm("TRIGGER:-PT10M\nDURATION:PT1M\nREPEAT:1\n")
+        };
+        assert_eq!(
+            alarm.trigger_times(starts(), None).len(),
+            MAX_REPEAT as usize + 1
+        );
+    }
+}
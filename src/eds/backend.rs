@@ -0,0 +1,48 @@
+use anyhow::Context;
+
+use crate::{
+    backend,
+    eds::calendar::{Calendar, SourceKind},
+};
+
+// The calendars and the task lists on the Evolution Data Server.
+pub struct Eds<'a> {
+    conn: &'a zbus::Connection,
+}
+
+impl<'a> Eds<'a> {
+    pub fn new(conn: &'a zbus::Connection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl backend::Backend for Eds<'_> {
+    fn name(&self) -> String {
+        "Evolution Data Server".to_owned()
+    }
+
+    async fn calendars(&self) -> anyhow::Result<Vec<Box<dyn backend::Calendar + '_>>> {
+        sources(self.conn, SourceKind::Calendar)
+            .await
+            .context("Could not list all calendars")
+    }
+
+    async fn task_lists(&self) -> anyhow::Result<Vec<Box<dyn backend::Calendar + '_>>> {
+        sources(self.conn, SourceKind::TaskList)
+            .await
+            .context("Could not list all task lists")
+    }
+}
+
+// Returns the sources of the kind, boxed so that they can be used as any other calendar.
+async fn sources<'a>(
+    conn: &'a zbus::Connection,
+    kind: SourceKind,
+) -> anyhow::Result<Vec<Box<dyn backend::Calendar + 'a>>> {
+    Ok(Calendar::fetch_all_of(conn, kind)
+        .await?
+        .into_iter()
+        .map(|calendar| Box::new(calendar) as Box<dyn backend::Calendar + 'a>)
+        .collect())
+}
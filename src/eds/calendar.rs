@@ -1,22 +1,110 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use crate::eds::{event::Event, ipc};
+use crate::{
+    backend,
+    eds::{
+        event::{CalendarRef, Event, NewEvent, component_recurrence_id, recurrence_id},
+        ipc,
+        memo::Memo,
+        query::Query,
+        task::Task,
+    },
+    utils,
+};
 use anyhow::Context;
 use calcard::icalendar;
-use chrono::Days;
+use futures_util::StreamExt;
 use gio::glib;
 
+// The E_CAL_OBJ_MOD_* modes, which decide the instances of a recurring event that a
+// modification or a removal applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ModType {
+    /// Only the given instance.
+    This,
+    /// The given instance and all the instances after it.
+    ThisAndFuture,
+    /// The whole series.
+    All,
+}
+
+impl ModType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModType::This => "this",
+            ModType::ThisAndFuture => "this-and-future",
+            ModType::All => "all",
+        }
+    }
+}
+
+// The kinds of sources on the EDS that are backed by the calendar factory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    // Calendars, made up of events.
+    Calendar,
+    // Task lists, made up of to-dos.
+    TaskList,
+    // Memo lists, made up of journal entries.
+    MemoList,
+}
+
+impl SourceKind {
+    // Returns the group on the source data that marks a source of this kind.
+    fn group(&self) -> &'static str {
+        match self {
+            SourceKind::Calendar => "Calendar",
+            SourceKind::TaskList => "Task List",
+            SourceKind::MemoList => "Memo List",
+        }
+    }
+
+    // Returns the type of the components stored on a source of this kind.
+    fn component_type(&self) -> icalendar::ICalendarComponentType {
+        match self {
+            SourceKind::Calendar => icalendar::ICalendarComponentType::VEvent,
+            SourceKind::TaskList => icalendar::ICalendarComponentType::VTodo,
+            SourceKind::MemoList => icalendar::ICalendarComponentType::VJournal,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Calendar<'a> {
     conn: &'a zbus::Connection,
 
+    pub kind: SourceKind,
     pub uid: String,
     pub display_name: Option<String>,
+
+    // The source of the account this calendar belongs to, if any, and its name.
+    pub parent: Option<String>,
+    pub account: Option<String>,
+
+    pub backend: Option<String>,
+    pub color: Option<String>,
+
+    // Whether the source, and its account, are turned on, and, whether the calendar
+    // was ticked on the calendar apps.
+    pub enabled: bool,
+    pub selected: bool,
+
+    // The addresses of the user on the account, to tell which of the attendees they are.
+    pub addresses: Vec<String>,
 }
 
 impl<'a> Calendar<'a> {
     // Returns a list of all the calendars that were found on the Evolution Data Server.
     pub async fn fetch_all(conn: &'a zbus::Connection) -> anyhow::Result<Vec<Self>> {
+        Self::fetch_all_of(conn, SourceKind::Calendar).await
+    }
+
+    // Returns a list of all the sources of the kind that were found on the Evolution
+    // Data Server.
+    pub async fn fetch_all_of(
+        conn: &'a zbus::Connection,
+        kind: SourceKind,
+    ) -> anyhow::Result<Vec<Self>> {
         let sources_proxy = ipc::SourcesProxy::new(conn)
             .await
             .context("Could not build sources proxy")?;
@@ -26,7 +114,8 @@ impl<'a> Calendar<'a> {
             .await
             .context("Could not fetch the list of sources")?;
 
-        let mut calendars: Vec<Self> = vec![];
+        // All the sources are loaded first, so that the accounts can be looked up.
+        let mut source_data: HashMap<String, Option<glib::KeyFile>> = HashMap::new();
         for (_, object_value) in sources.iter() {
             // The other interfaces are meant for mutation and stuff.
             let Some(source_value) = object_value.get("org.gnome.evolution.dataserver.Source")
@@ -52,132 +141,619 @@ impl<'a> Calendar<'a> {
                     }
                 });
 
-            // Filter for sources that have a calendars attached to them.
-            if !data
-                .as_ref()
-                .and_then(|value| Some(value.has_group("Calendar")))
-                .unwrap_or_default()
-            {
+            source_data.insert(uid, data);
+        }
+
+        // The addresses the user sends mail from, along with the account they are set up on.
+        let identities: Vec<(Option<String>, String)> = source_data
+            .values()
+            .flatten()
+            .filter_map(|data| {
+                let parent = key_string(data, "Data Source", "Parent").filter(|p| !p.is_empty());
+                Some((parent, key_string(data, "Mail Identity", "Address")?))
+            })
+            .collect();
+
+        let mut calendars: Vec<Self> = vec![];
+        for (uid, data) in source_data.iter() {
+            // Filter for sources that have a calendar, a task list or a memo list attached
+            // to them, depending on the kind.
+            let Some(data) = data.as_ref().filter(|data| data.has_group(kind.group())) else {
                 continue;
-            }
+            };
+
+            let parent = key_string(data, "Data Source", "Parent").filter(|p| !p.is_empty());
+            let parent_data = parent
+                .as_ref()
+                .and_then(|parent| source_data.get(parent)?.as_ref());
+
+            let addresses = addresses(data, parent_data, parent.as_deref(), &identities);
 
             calendars.push(Self {
-                conn: conn,
-                uid: uid,
-                display_name: data
-                    .as_ref()
-                    .and_then(|value| value.string("Data Source", "DisplayName").ok())
-                    .and_then(|name| Some(name.to_string())),
+                conn,
+                kind,
+                uid: uid.clone(),
+                display_name: key_string(data, "Data Source", "DisplayName"),
+
+                parent,
+                account: parent_data
+                    .and_then(|parent| key_string(parent, "Data Source", "DisplayName")),
+
+                backend: key_string(data, kind.group(), "BackendName"),
+                color: key_string(data, kind.group(), "Color"),
+                // Sources are enabled and selected unless they say otherwise.
+                enabled: [Some(data), parent_data]
+                    .into_iter()
+                    .flatten()
+                    .all(|data| data.boolean("Data Source", "Enabled").unwrap_or(true)),
+                selected: data.boolean(kind.group(), "Selected").unwrap_or(true),
+                addresses,
             });
         }
 
         Ok(calendars)
     }
 
-    // Returns a list of all the events found on this calendar on the EDS.
-    async fn fetch_events(
-        &self,
-        starts: chrono::DateTime<chrono::Local>,
-        ends: chrono::DateTime<chrono::Local>,
-    ) -> anyhow::Result<Vec<super::event::Event>> {
+    // Returns a proxy to query this calendar on the EDS.
+    async fn proxy(&self) -> anyhow::Result<ipc::CalendarProxy<'a>> {
         let calendar_factory_proxy = ipc::CalendarFactoryProxy::new(self.conn)
             .await
             .context("Could not build calendar factory proxy")?;
 
-        let (calendar_path, _) = calendar_factory_proxy
-            .open_calendar(&self.uid)
-            .await
-            .context("Could not query calendar")?;
+        let (calendar_path, _) = match self.kind {
+            SourceKind::Calendar => calendar_factory_proxy.open_calendar(&self.uid).await,
+            SourceKind::TaskList => calendar_factory_proxy.open_task_list(&self.uid).await,
+            SourceKind::MemoList => calendar_factory_proxy.open_memo_list(&self.uid).await,
+        }
+        .context("Could not query calendar")?;
 
-        let calendar_proxy = ipc::CalendarProxy::builder(self.conn)
+        ipc::CalendarProxy::builder(self.conn)
             .path(calendar_path)
             .context("Could not set path on calendar proxy")?
             .build()
             .await
-            .context("Could not build calendar proxy")?;
+            .context("Could not build calendar proxy")
+    }
 
-        let q = format!(
-            "
-            (occur-in-time-range?
-                (make-time \"{}\")
-                (make-time \"{}\"))
-            ",
-            starts.format("%Y%m%dT%H%M%S"),
-            ends.format("%Y%m%dT%H%M%S")
-        );
+    // Returns the components of an event by its UID, each as a tree along with its alarms.
+    // The master component comes first, followed by its detached instances, if there are any.
+    pub async fn fetch_components(&self, uid: &str) -> anyhow::Result<Vec<icalendar::ICalendar>> {
+        let ics = self
+            .proxy()
+            .await?
+            .get_object(uid, "")
+            .await
+            .context("Could not find event")?;
+
+        let mut vevents = parse_components(&ics, &self.kind.component_type());
+        vevents.sort_by_key(|vevent| vevent.components[0].is_recurrence_override());
+
+        Ok(vevents)
+    }
 
+    // Replaces an event, or the instances of it picked by the mod type, on this calendar.
+    // The event is given as a tree, so that its alarms are kept.
+    pub async fn modify_event(
+        &self,
+        vevent: &icalendar::ICalendar,
+        mod_type: ModType,
+    ) -> anyhow::Result<()> {
+        let ics = vevent.to_string();
+
+        self.proxy()
+            .await?
+            .modify_objects(&[&ics], mod_type.as_str(), 0)
+            .await
+            .context("Could not modify event")
+    }
+
+    // Removes an event, or the instances of it picked by the recurrence id and the mod
+    // type, from this calendar.
+    pub async fn remove_event(
+        &self,
+        uid: &str,
+        rid: Option<&str>,
+        mod_type: ModType,
+    ) -> anyhow::Result<()> {
+        self.proxy()
+            .await?
+            .remove_objects(&[(uid, rid.unwrap_or_default())], mod_type.as_str(), 0)
+            .await
+            .context("Could not remove event")
+    }
+
+    // Returns a list of all the memos found on this memo list on the EDS.
+    pub async fn fetch_memos(&self) -> anyhow::Result<Vec<Memo>> {
+        let vjournal_result = self
+            .proxy()
+            .await?
+            .get_object_list(&Query::All.to_string())
+            .await
+            .context("Could not query memos")?;
+
+        Ok(vjournal_result
+            .iter()
+            .flat_map(|item| parse_components(item, &self.kind.component_type()))
+            .map(|vjournal| Memo::from(&vjournal.components[0]))
+            .collect())
+    }
+
+    // Opens a live view over the events scheduled from start of yesterday to end of
+    // tomorrow. The view keeps track of the changes reported by the EDS, so, the events
+    // do not need to be fetched again.
+    pub async fn view_near_events(&self) -> anyhow::Result<View<'a>> {
+        let (starts, ends) = utils::near_range()?;
+        self.view(&Query::occur_in_time_range(starts, ends), starts, ends)
+            .await
+    }
+
+    // Opens a live view over all the tasks on this task list.
+    pub async fn view_tasks(&self) -> anyhow::Result<View<'a>> {
+        let (starts, ends) = utils::near_range()?;
+        self.view(&Query::All, starts, ends).await
+    }
+
+    // Opens a live view over the components matching the query. The range is used to
+    // expand the recurring events on the view.
+    async fn view(
+        &self,
+        query: &Query,
+        starts: chrono::DateTime<chrono::Local>,
+        ends: chrono::DateTime<chrono::Local>,
+    ) -> anyhow::Result<View<'a>> {
+        let calendar_proxy = self.proxy().await?;
+
+        let view_path = calendar_proxy
+            .get_view(&query.to_string())
+            .await
+            .context("Could not create calendar view")?;
+
+        let view_proxy = ipc::CalendarViewProxy::builder(self.conn)
+            .path(view_path)
+            .context("Could not set path on calendar view proxy")?
+            .build()
+            .await
+            .context("Could not build calendar view proxy")?;
+
+        // The signals need to be subscribed to before the view is started, otherwise,
+        // we could miss the initial set of objects.
+        let mut view = View {
+            added: view_proxy
+                .receive_objects_added()
+                .await
+                .context("Could not subscribe to added objects")?,
+            modified: view_proxy
+                .receive_objects_modified()
+                .await
+                .context("Could not subscribe to modified objects")?,
+            removed: view_proxy
+                .receive_objects_removed()
+                .await
+                .context("Could not subscribe to removed objects")?,
+            proxy: view_proxy,
+            component_type: self.kind.component_type(),
+            components: BTreeMap::new(),
+            starts,
+            ends,
+            calendar: CalendarRef::new(
+                &self.uid,
+                self.display_name.as_deref(),
+                self.addresses.clone(),
+            ),
+        };
+
+        let mut complete = view
+            .proxy
+            .receive_complete()
+            .await
+            .context("Could not subscribe to view completion")?;
+
+        view.proxy
+            .start()
+            .await
+            .context("Could not start calendar view")?;
+
+        // Apply the initial set of objects before handing out the view.
+        loop {
+            tokio::select! {
+                biased;
+
+                Some(signal) = view.added.next() => view.apply_added(signal.args()?.objects()),
+                Some(signal) = view.modified.next() => view.apply_added(signal.args()?.objects()),
+                Some(signal) = view.removed.next() => view.apply_removed(signal.args()?.uids()),
+
+                signal = complete.next() => {
+                    if let Some(signal) = signal
+                        && let Some(error) = signal.args()?.error().first()
+                        && !error.is_empty()
+                    {
+                        anyhow::bail!("Calendar view failed: {}", error);
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        Ok(view)
+    }
+}
+
+#[async_trait::async_trait]
+impl backend::Calendar for Calendar<'_> {
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    // Returns the "account/name" path of the calendar, which tells apart the calendars
+    // with the same name on different accounts.
+    fn path(&self) -> Option<String> {
+        match (&self.account, &self.display_name) {
+            (Some(account), Some(name)) => Some(format!("{}/{}", account, name)),
+            _ => None,
+        }
+    }
+
+    // Returns whether the calendar should be shown, that is, it is both enabled and
+    // selected.
+    fn is_visible(&self) -> bool {
+        self.enabled && self.selected
+    }
+
+    fn addresses(&self) -> Vec<String> {
+        self.addresses.clone()
+    }
+
+    // Returns a list of all the events found on this calendar on the EDS in the range that
+    // match the query. The query is run on the EDS, along with the range.
+    async fn fetch_events(
+        &self,
+        starts: chrono::DateTime<chrono::Local>,
+        ends: chrono::DateTime<chrono::Local>,
+        query: &Query,
+    ) -> anyhow::Result<Vec<super::event::Event>> {
+        let calendar_proxy = self.proxy().await?;
+
+        let query = Query::occur_in_time_range(starts, ends).and(query.clone());
         let vevent_result = calendar_proxy
-            .get_object_list(&q)
+            .get_object_list(&query.to_string())
             .await
             .context("Could not query events")?;
 
-        let mut seen = HashSet::<String>::new();
-        let vevents: Vec<icalendar::ICalendarComponent> = vevent_result
+        let vevents: Vec<icalendar::ICalendar> = vevent_result
+            .iter()
+            .flat_map(|item| parse_components(item, &self.kind.component_type()))
+            .collect();
+
+        Ok(events_from_vevents(vevents.iter(), starts, ends))
+    }
+
+    // Returns a list of all the tasks found on this task list on the EDS.
+    async fn fetch_tasks(&self) -> anyhow::Result<Vec<Task>> {
+        let vtodo_result = self
+            .proxy()
+            .await?
+            .get_object_list(&Query::All.to_string())
+            .await
+            .context("Could not query tasks")?;
+
+        let vtodos: Vec<icalendar::ICalendar> = vtodo_result
             .iter()
-            .filter_map(|item| icalendar::ICalendar::parse(item).ok())
-            .flat_map(|cal| -> Vec<icalendar::ICalendarComponent> {
-                cal.components
+            .flat_map(|item| parse_components(item, &self.kind.component_type()))
+            .collect();
+
+        Ok(tasks_from_vtodos(vtodos.iter()))
+    }
+
+    // Returns whether the objects on this calendar can be changed. Some backends, like
+    // the ones for holidays and birthdays, are read-only.
+    async fn is_writable(&self) -> anyhow::Result<bool> {
+        self.proxy()
+            .await?
+            .writable()
+            .await
+            .context("Could not query whether the calendar is writable")
+    }
+
+    // Stores a new event on this calendar and returns its UID.
+    async fn create_event(&self, event: &NewEvent) -> anyhow::Result<String> {
+        let uid = uuid::Uuid::new_v4().to_string();
+
+        let ics = icalendar::ICalendar {
+            components: vec![event.to_component(&uid)],
+        }
+        .to_string();
+
+        let uids = self
+            .proxy()
+            .await?
+            .create_objects(&[&ics], 0)
+            .await
+            .context("Could not create event")?;
+
+        Ok(uids.into_iter().next().unwrap_or(uid))
+    }
+}
+
+// A live set of events, or tasks, on a calendar that is kept up to date using the
+// signals from a calendar view on the EDS.
+pub struct View<'a> {
+    proxy: ipc::CalendarViewProxy<'a>,
+
+    added: ipc::ObjectsAddedStream,
+    modified: ipc::ObjectsModifiedStream,
+    removed: ipc::ObjectsRemovedStream,
+
+    // The components are kept as trees by their "<uid>[\n<rid>]" id, which is the same
+    // format the EDS uses to report removals.
+    component_type: icalendar::ICalendarComponentType,
+    components: BTreeMap<String, icalendar::ICalendar>,
+
+    starts: chrono::DateTime<chrono::Local>,
+    ends: chrono::DateTime<chrono::Local>,
+
+    calendar: CalendarRef,
+}
+
+impl View<'_> {
+    // Waits until the EDS reports a change on this view and applies it.
+    // This is cancel safe, the change is only applied once it was received.
+    pub async fn changed(&mut self) -> anyhow::Result<()> {
+        tokio::select! {
+            Some(signal) = self.added.next() => self.apply_added(signal.args()?.objects()),
+            Some(signal) = self.modified.next() => self.apply_added(signal.args()?.objects()),
+            Some(signal) = self.removed.next() => self.apply_removed(signal.args()?.uids()),
+            else => anyhow::bail!("Calendar view was closed"),
+        }
+
+        Ok(())
+    }
+
+    // Returns the recurrence expanded list of events currently in the view.
+    pub fn events(&self) -> Vec<Event> {
+        match self.component_type {
+            icalendar::ICalendarComponentType::VEvent => {
+                events_from_vevents(self.components.values(), self.starts, self.ends)
                     .into_iter()
-                    .filter(|item| item.component_type == icalendar::ICalendarComponentType::VEvent)
+                    .map(|event| event.on(self.calendar.clone()))
                     .collect()
-            })
-            // For some reason, Evolution returns duplicates for some events. There could
-            // be a deeper cause to this, but, here, we discard them by the UID.
-            .filter(|vevent| match vevent.uid() {
-                Some(uid) => {
-                    if seen.contains(uid) {
-                        false
-                    } else {
-                        seen.insert(uid.to_owned());
-                        true
-                    }
-                }
-                _ => false,
-            })
-            .collect();
+            }
+            _ => vec![],
+        }
+    }
 
-        Ok(vevents
+    // Returns the list of tasks currently in the view.
+    pub fn tasks(&self) -> Vec<Task> {
+        match self.component_type {
+            icalendar::ICalendarComponentType::VTodo => tasks_from_vtodos(self.components.values()),
+            _ => vec![],
+        }
+    }
+
+    // Releases the view on the EDS.
+    pub async fn close(self) -> anyhow::Result<()> {
+        self.proxy
+            .dispose()
+            .await
+            .context("Could not dispose calendar view")
+    }
+
+    fn apply_added(&mut self, objects: &[String]) {
+        for component in objects
             .iter()
-            .flat_map(|vevent| {
-                // TODO: Is this enough to know if we should be expanding?
-                if vevent
-                    .property(&icalendar::ICalendarProperty::Rrule)
-                    .is_some()
-                {
-                    expand_events(vevent, starts, ends).unwrap_or_default()
-                } else {
-                    vec![Event::from(vevent)]
-                }
-            })
-            .collect())
+            .flat_map(|item| parse_components(item, &self.component_type))
+        {
+            if let Some(id) = component_id(&component.components[0]) {
+                self.components.insert(id, component);
+            }
+        }
     }
 
-    // Returns a list of events that were scheduled from start of yesterday to end of tomorrow.
-    pub async fn fetch_near_events(&self) -> anyhow::Result<Vec<super::event::Event>> {
-        let now = chrono::Local::now();
+    fn apply_removed(&mut self, ids: &[String]) {
+        for id in ids {
+            self.components.remove(id);
+        }
+    }
+}
 
-        let starts = now
-            .with_time(chrono::NaiveTime::default())
-            .earliest()
-            .and_then(|dt| dt.checked_sub_days(Days::new(1)))
-            .context("Could not determine start of yesterday")?;
+// Returns the addresses the user is known by on a source. Those are the address the calendar
+// is shared with, the identity and the user name of its account, when they are addresses,
+// and, the addresses of the mail identities on the same account. The calendars that are
+// not a part of an account, like the local ones, are where the invitations from any of
+// the mail accounts end up, so, all the mail identities are used for them.
+fn addresses(
+    data: &glib::KeyFile,
+    parent_data: Option<&glib::KeyFile>,
+    parent: Option<&str>,
+    identities: &[(Option<String>, String)],
+) -> Vec<String> {
+    let mut addresses: Vec<String> = [
+        key_string(data, "WebDAV Backend", "EmailAddress"),
+        key_string(data, "Authentication", "User"),
+    ]
+    .into_iter()
+    .chain(parent_data.into_iter().flat_map(|parent| {
+        [
+            key_string(parent, "Collection", "Identity"),
+            key_string(parent, "Authentication", "User"),
+        ]
+    }))
+    .flatten()
+    .filter(|address| address.contains('@'))
+    .collect();
 
-        let ends = now
-            .with_time(chrono::NaiveTime::default())
-            .earliest()
-            .and_then(|dt| dt.checked_add_days(chrono::Days::new(2)))
-            .context("Could not determine the start of day after tomorrow")?;
+    addresses.extend(
+        identities
+            .iter()
+            .filter(|(account, _)| parent.is_none() || account.as_deref() == parent)
+            .map(|(_, address)| address.clone()),
+    );
 
-        self.fetch_events(starts, ends).await
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+// Returns the value of a key on the source data as a string.
+fn key_string(data: &glib::KeyFile, group: &str, key: &str) -> Option<String> {
+    data.string(group, key).ok().map(|value| value.to_string())
+}
+
+// Parses an iCalendar object, like the ones returned by the EDS, into its components of
+// the given type. Every component is returned as a tree of its own, with the component at
+// the root, so that its sub-components, like the alarms, are kept along with it.
+pub(crate) fn parse_components(
+    item: &str,
+    component_type: &icalendar::ICalendarComponentType,
+) -> Vec<icalendar::ICalendar> {
+    match icalendar::ICalendar::parse(item) {
+        Ok(cal) => cal
+            .components
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| &item.component_type == component_type)
+            .map(|(id, _)| component_tree(&cal, id as u16))
+            .collect(),
+        _ => vec![],
     }
 }
 
-// Returns a recurrence expanded list of events.
+// Returns the component and all of its sub-components as a tree of its own.
+fn component_tree(cal: &icalendar::ICalendar, root: u16) -> icalendar::ICalendar {
+    let mut components = Vec::new();
+
+    // The sub-components are renumbered as they are visited.
+    let mut pending = vec![(root, None::<usize>)];
+    while let Some((id, parent)) = pending.pop() {
+        let Some(component) = cal.component_by_id(id) else {
+            continue;
+        };
+
+        let index = components.len();
+        if let Some(parent) = parent {
+            let parent: &mut icalendar::ICalendarComponent = &mut components[parent];
+            parent.component_ids.push(index as u16);
+        }
+
+        components.push(icalendar::ICalendarComponent {
+            component_ids: vec![],
+            ..component.clone()
+        });
+
+        pending.extend(
+            component
+                .component_ids
+                .iter()
+                .rev()
+                .map(|child| (*child, Some(index))),
+        );
+    }
+
+    icalendar::ICalendar { components }
+}
+
+// Returns the "<uid>[\n<rid>]" id of the component, the same way the EDS reports it.
+fn component_id(component: &icalendar::ICalendarComponent) -> Option<String> {
+    let uid = component.uid()?;
+
+    match component_recurrence_id(component) {
+        Some(rid) => Some(format!("{}\n{}", uid, rid)),
+        None => Some(uid.to_owned()),
+    }
+}
+
+// Returns the recurrence expanded list of events from the components.
+pub(crate) fn events_from_vevents<'c>(
+    vevents: impl Iterator<Item = &'c icalendar::ICalendar>,
+    starts: chrono::DateTime<chrono::Local>,
+    ends: chrono::DateTime<chrono::Local>,
+) -> Vec<Event> {
+    let mut seen = HashSet::<(String, Option<String>)>::new();
+
+    // For some reason, Evolution returns duplicates for some events. There could
+    // be a deeper cause to this, but, here, we discard them by the UID and the
+    // RECURRENCE-ID, so that the detached instances are kept along their series.
+    let vevents: Vec<_> = vevents
+        .filter(|vevent| match vevent.components[0].uid() {
+            Some(uid) => seen.insert((
+                uid.to_owned(),
+                component_recurrence_id(&vevent.components[0]),
+            )),
+            _ => false,
+        })
+        .collect();
+
+    // The detached instances take the place of the occurrences they were detached from,
+    // whether they come before or after their series.
+    let detached: HashSet<(&str, String)> = vevents
+        .iter()
+        .filter_map(|vevent| {
+            let component = &vevent.components[0];
+            Some((component.uid()?, component_recurrence_id(component)?))
+        })
+        .collect();
+
+    vevents
+        .iter()
+        .flat_map(|vevent| {
+            let master = &vevent.components[0];
+
+            // TODO: Is this enough to know if we should be expanding?
+            if master
+                .property(&icalendar::ICalendarProperty::Rrule)
+                .is_some()
+                && component_recurrence_id(master).is_none()
+            {
+                let uid = master.uid().unwrap_or_default();
+
+                expand_events(vevent, starts, ends)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|occurrence| {
+                        occurrence
+                            .starts
+                            .and_then(|starts| recurrence_id(master, starts.to_utc()).ok())
+                            .is_none_or(|rid| !detached.contains(&(uid, rid)))
+                    })
+                    .collect()
+            } else {
+                vec![Event::from_tree(vevent)]
+            }
+        })
+        .collect()
+}
+
+// Returns the list of tasks from the components.
+pub(crate) fn tasks_from_vtodos<'c>(
+    vtodos: impl Iterator<Item = &'c icalendar::ICalendar>,
+) -> Vec<Task> {
+    let mut seen = HashSet::<String>::new();
+
+    vtodos
+        // Recurring tasks are not expanded, so, only their first instance is kept.
+        .filter(|vtodo| match vtodo.components[0].uid() {
+            Some(uid) => seen.insert(uid.to_owned()),
+            _ => false,
+        })
+        .map(|vtodo| Task::from(&vtodo.components[0]))
+        .collect()
+}
+
+// The most occurrences of a recurring event that are expanded in a range.
+const MAX_RECURRENCES: u16 = u16::MAX;
+
+// Returns a recurrence expanded list of events. The alarms of the event are carried over
+// to every occurrence.
 fn expand_events(
-    root: &icalendar::ICalendarComponent,
+    tree: &icalendar::ICalendar,
     starts: chrono::DateTime<chrono::Local>,
     ends: chrono::DateTime<chrono::Local>,
 ) -> Option<Vec<Event>> {
+    let root = &tree.components[0];
     let mut rule = String::new();
 
     root.property(&calcard::icalendar::ICalendarProperty::Dtstart)
@@ -206,11 +782,104 @@ fn expand_events(
             .and_local_timezone(rrule::Tz::Local(chrono::Local))
             .earliest()?;
 
+        // Every occurrence up to the end of the range is expanded, the limit only stops the
+        // rules that recur so often that the range holds more than can be listed.
+        let recurrences = rrule.after(starts).before(ends).all(MAX_RECURRENCES);
+        if recurrences.limited {
+            eprintln!(
+                "\"{}\" recurs too often, only its first {} occurrences are used",
+                root.property(&calcard::icalendar::ICalendarProperty::Summary)
+                    .and_then(|it| it.values.first())
+                    .and_then(|it| it.as_text())
+                    .unwrap_or_default(),
+                recurrences.dates.len(),
+            );
+        }
+
         Some(Event::from_recurrences(
-            &root,
-            rrule.after(starts).before(ends).all(32),
+            &Event::from_tree(tree),
+            recurrences,
         ))
     } else {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(vevents: &[&str]) -> Vec<(String, String)> {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\n{}END:VCALENDAR\r\n",
+            vevents.concat().replace('\n', "\r\n"),
+        );
+        let trees = parse_components(&ics, &icalendar::ICalendarComponentType::VEvent);
+
+        let starts =
+            chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2025, 1, 5, 0, 0, 0).unwrap();
+        let ends =
+            chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2025, 1, 10, 0, 0, 0).unwrap();
+
+        let mut events: Vec<_> = events_from_vevents(trees.iter(), starts, ends)
+            .into_iter()
+            .map(|event| {
+                (
+                    event
+                        .starts
+                        .unwrap()
+                        .to_utc()
+                        .format("%d %H:%M")
+                        .to_string(),
+                    event.title.unwrap_or_default(),
+                )
+            })
+            .collect();
+        events.sort();
+        events
+    }
+
+    const MASTER: &str = "BEGIN:VEVENT\nUID:standup\nDTSTART:20250106T100000Z\n\
+                          DURATION:PT15M\nRRULE:FREQ=DAILY;COUNT=3\nSUMMARY:Standup\nEND:VEVENT\n";
+
+    const DETACHED: &str = "BEGIN:VEVENT\nUID:standup\nRECURRENCE-ID:20250107T100000Z\n\
+                            DTSTART:20250107T150000Z\nDURATION:PT15M\nSUMMARY:Moved\nEND:VEVENT\n";
+
+    fn expected() -> Vec<(String, String)> {
+        [
+            ("06 10:00", "Standup"),
+            ("07 15:00", "Moved"),
+            ("08 10:00", "Standup"),
+        ]
+        .map(|(starts, title)| (starts.to_owned(), title.to_owned()))
+        .to_vec()
+    }
+
+    #[test]
+    fn replaces_the_occurrences_with_their_detached_instances() {
+        assert_eq!(events(&[MASTER, DETACHED]), expected());
+    }
+
+    #[test]
+    fn replaces_the_occurrences_with_the_instances_listed_before_them() {
+        assert_eq!(events(&[DETACHED, MASTER]), expected());
+    }
+
+    #[test]
+    fn expands_every_occurrence_in_the_range() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:one-on-one\r\n\
+                   DTSTART:20250106T100000Z\r\nDURATION:PT30M\r\nRRULE:FREQ=DAILY\r\n\
+                   SUMMARY:1:1\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let trees = parse_components(ics, &icalendar::ICalendarComponentType::VEvent);
+
+        let starts =
+            chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2025, 1, 5, 0, 0, 0).unwrap();
+        let events = events_from_vevents(trees.iter(), starts, starts + chrono::Days::new(90));
+        assert_eq!(events.len(), 89);
+    }
+
+    #[test]
+    fn discards_the_duplicates() {
+        assert_eq!(events(&[MASTER, DETACHED, MASTER, DETACHED]), expected());
+    }
+}
@@ -1,71 +1,624 @@
 use anyhow::Context;
-use calcard::icalendar;
-use chrono::{Datelike, TimeZone};
+use calcard::{common::PartialDateTime, icalendar};
+use chrono::{Datelike, TimeZone, Timelike};
 
-#[derive(Debug)]
+use crate::eds::alarm::{Alarm, alarms_of};
+
+#[derive(Debug, Clone)]
 pub struct Event {
     pub uid: Option<String>,
     pub status: Option<icalendar::ICalendarStatus>,
+    // Transparent events do not take up any time on the calendar.
+    pub transparent: bool,
 
     pub title: Option<String>,
     pub description: Option<String>,
+    pub location: Option<String>,
+    pub url: Option<String>,
+
+    pub organizer: Option<Participant>,
+    pub attendees: Vec<Participant>,
+
+    pub categories: Vec<String>,
+    pub class: Option<icalendar::ICalendarClassification>,
+    // From 1, the highest, to 9, the lowest. The events without one have it undefined.
+    pub priority: Option<u8>,
 
     pub starts: Option<chrono::DateTime<rrule::Tz>>,
     pub ends: Option<chrono::DateTime<rrule::Tz>>,
+    // All-day events start on a date rather than at a time. They start at the local
+    // midnight of their first day, and end at the one after their last day.
+    pub all_day: bool,
+
+    pub alarms: Vec<Alarm>,
+
+    // The calendar the event was found on. The components do not tell, so, it is only
+    // known once the event was fetched from a calendar.
+    pub calendar: Option<CalendarRef>,
+}
+
+// A person on an event, either its organizer or one of its attendees.
+#[derive(Debug, Clone)]
+pub struct Participant {
+    // The calendar address of the person, usually a "mailto:" URI.
+    pub address: String,
+    pub name: Option<String>,
+
+    // Only the attendees have a role and reply to the invitation.
+    pub role: Option<icalendar::ICalendarParticipationRole>,
+    pub status: Option<icalendar::ICalendarParticipationStatus>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CalendarRef {
+    pub uid: String,
+    pub name: Option<String>,
+
+    // The addresses the user is known by on the calendar.
+    pub addresses: Vec<String>,
 }
 
 impl From<&icalendar::ICalendarComponent> for Event {
     fn from(component: &icalendar::ICalendarComponent) -> Self {
+        let starts = match dt_property(component, &icalendar::ICalendarProperty::Dtstart) {
+            Some(Ok(dtstarts)) => Some(dtstarts),
+            _ => None,
+        };
+        let all_day = is_date_property(component, &icalendar::ICalendarProperty::Dtstart);
+
         Self {
             uid: component.uid().map(|uid| uid.to_owned()),
-            status: component.status().map(|status| status.clone()),
+            status: component.status().cloned(),
+            transparent: component.transparency()
+                == Some(&icalendar::ICalendarTransparency::Transparent),
 
-            title: str_property(&component, &icalendar::ICalendarProperty::Summary),
-            description: str_property(&component, &icalendar::ICalendarProperty::Description),
+            title: str_property(component, &icalendar::ICalendarProperty::Summary),
+            description: str_property(component, &icalendar::ICalendarProperty::Description),
+            location: str_property(component, &icalendar::ICalendarProperty::Location),
+            url: str_property(component, &icalendar::ICalendarProperty::Url),
 
-            starts: match dt_property(&component, &icalendar::ICalendarProperty::Dtstart) {
-                Some(Ok(dtstarts)) => Some(dtstarts),
-                _ => None,
-            },
-            ends: match dt_property(&component, &icalendar::ICalendarProperty::Dtend) {
+            organizer: component
+                .property(&icalendar::ICalendarProperty::Organizer)
+                .and_then(Participant::from_entry),
+            attendees: component
+                .properties(&icalendar::ICalendarProperty::Attendee)
+                .filter_map(Participant::from_entry)
+                .collect(),
+
+            // The categories can be spread over several properties.
+            categories: component
+                .properties(&icalendar::ICalendarProperty::Categories)
+                .flat_map(|entry| entry.values.iter())
+                .filter_map(|value| value.as_text())
+                .map(|category| category.to_owned())
+                .collect(),
+            class: component
+                .property(&icalendar::ICalendarProperty::Class)
+                .and_then(|entry| match entry.values.first()? {
+                    icalendar::ICalendarValue::Classification(class) => Some(class.clone()),
+                    _ => None,
+                }),
+            priority: component
+                .property(&icalendar::ICalendarProperty::Priority)
+                .and_then(|entry| entry.values.first()?.as_integer())
+                .and_then(|priority| u8::try_from(priority).ok())
+                .filter(|priority| (1..=9).contains(priority)),
+
+            starts,
+            ends: match dt_property(component, &icalendar::ICalendarProperty::Dtend) {
                 Some(Ok(dtends)) => Some(dtends),
-                _ => None,
+                _ => starts.and_then(|starts| implicit_end(component, starts, all_day)),
             },
+            all_day,
+
+            // The alarms are sub-components, so, they are only found on the tree.
+            alarms: vec![],
+
+            calendar: None,
         }
     }
 }
 
 impl Event {
-    pub fn from_recurrences(
-        component: &icalendar::ICalendarComponent,
-        recurrences: rrule::RRuleResult,
-    ) -> Vec<Self> {
-        let root = Event::from(component);
+    // Builds the event from the tree of the component, along with its alarms.
+    pub fn from_tree(tree: &icalendar::ICalendar) -> Self {
+        Self {
+            alarms: alarms_of(tree),
+            ..Self::from(&tree.components[0])
+        }
+    }
 
+    pub fn from_recurrences(root: &Event, recurrences: rrule::RRuleResult) -> Vec<Self> {
         let duration = match (root.starts, root.ends) {
             (Some(dtstarts), Some(dtends)) => Some(dtends.to_utc() - dtstarts.to_utc()),
             _ => None,
         };
 
+        // The all-day occurrences span the same number of days instead, so that they
+        // still end at midnight on the days the clocks change.
+        let days = match (root.starts, root.ends) {
+            (Some(dtstarts), Some(dtends)) if root.all_day => {
+                u64::try_from((dtends.date_naive() - dtstarts.date_naive()).num_days())
+                    .ok()
+                    .map(chrono::Days::new)
+            }
+            _ => None,
+        };
+
         recurrences
             .dates
             .into_iter()
             .map(|starts| Self {
-                uid: root.uid.clone(),
-                status: root.status.clone(),
-
-                title: root.title.clone(),
-                description: root.description.clone(),
-
                 starts: Some(starts),
-                ends: duration.map(|duration| starts + duration),
+                ends: match days {
+                    Some(days) => starts.checked_add_days(days),
+                    None => duration.map(|duration| starts + duration),
+                },
+                ..root.clone()
             })
             .collect()
     }
+
+    // Marks the event as found on the calendar.
+    pub fn on(self, calendar: CalendarRef) -> Self {
+        Self {
+            calendar: Some(calendar),
+            ..self
+        }
+    }
+
+    // Returns the event as JSON, for the tools built on top of calcal.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "uid": self.uid,
+            "status": self.status.as_ref().map(|status| status.as_str()),
+            "transparent": self.transparent,
+
+            "title": self.title,
+            "description": self.description,
+            "location": self.location,
+            "url": self.url,
+
+            "organizer": self.organizer.as_ref().map(Participant::to_json),
+            "attendees": self.attendees.iter().map(Participant::to_json).collect::<Vec<_>>(),
+
+            "categories": self.categories,
+            "class": self.class.as_ref().map(|class| class.as_str()),
+            "priority": self.priority,
+
+            "starts": self.starts.map(|starts| starts.to_rfc3339()),
+            "ends": self.ends.map(|ends| ends.to_rfc3339()),
+            "all_day": self.all_day,
+
+            "calendar": self.calendar.as_ref().map(|calendar| serde_json::json!({
+                "uid": calendar.uid,
+                "name": calendar.name,
+            })),
+        })
+    }
+
+    // Returns whether this is an all-day event that takes up the day. An event without
+    // an end only takes up the day it starts on.
+    pub fn is_all_day_on(&self, day: chrono::NaiveDate) -> bool {
+        let Some(starts) = self.starts.filter(|_| self.all_day) else {
+            return false;
+        };
+
+        let first = starts.with_timezone(&chrono::Local).date_naive();
+        let last = self
+            .ends
+            .and_then(|ends| ends.with_timezone(&chrono::Local).date_naive().pred_opt())
+            .map_or(first, |last| last.max(first));
+
+        first <= day && day <= last
+    }
+
+    // Returns the reply of the user to the invitation, from the attendee with one of their
+    // addresses on the calendar, or, one of the other addresses. An attendee that did not
+    // reply yet might not have a status at all.
+    pub fn reply(&self, addresses: &[String]) -> Option<icalendar::ICalendarParticipationStatus> {
+        let known = self
+            .calendar
+            .iter()
+            .flat_map(|calendar| calendar.addresses.iter())
+            .chain(addresses.iter());
+
+        let attendee = self
+            .attendees
+            .iter()
+            .find(|attendee| known.clone().any(|address| attendee.is(address)))?;
+
+        Some(
+            attendee
+                .status
+                .clone()
+                .unwrap_or(icalendar::ICalendarParticipationStatus::NeedsAction),
+        )
+    }
+
+    // Returns the times the alarms of this event go off, along with the alarms, in order.
+    pub fn alarm_times(&self) -> Vec<(chrono::DateTime<chrono::Utc>, &Alarm)> {
+        let mut times: Vec<_> = self
+            .alarms
+            .iter()
+            .flat_map(|alarm| {
+                alarm
+                    .trigger_times(self.starts, self.ends)
+                    .into_iter()
+                    .map(move |at| (at, alarm))
+            })
+            .collect();
+
+        times.sort_by_key(|(at, _)| *at);
+        times
+    }
+}
+
+impl Participant {
+    // Parses an ORGANIZER or an ATTENDEE property.
+    fn from_entry(entry: &icalendar::ICalendarEntry) -> Option<Self> {
+        let mut participant = Self {
+            address: entry.values.first()?.as_text()?.to_owned(),
+            name: None,
+            role: None,
+            status: None,
+        };
+
+        for param in entry.params.iter() {
+            match param {
+                icalendar::ICalendarParameter::Cn(name) => participant.name = Some(name.clone()),
+                icalendar::ICalendarParameter::Role(role) => participant.role = Some(role.clone()),
+                icalendar::ICalendarParameter::Partstat(status) => {
+                    participant.status = Some(status.clone())
+                }
+                _ => {}
+            }
+        }
+
+        Some(participant)
+    }
+
+    // Returns whether the address is the one of this person, with or without the "mailto:".
+    fn is(&self, address: &str) -> bool {
+        let bare = |address: &str| {
+            let address = address.trim();
+            match address.get(..7) {
+                Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => address[7..].to_owned(),
+                _ => address.to_owned(),
+            }
+        };
+
+        bare(&self.address).eq_ignore_ascii_case(&bare(address))
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "address": self.address,
+            "name": self.name,
+            "role": self.role.as_ref().map(|role| role.as_str()),
+            "status": self.status.as_ref().map(|status| status.as_str()),
+        })
+    }
+}
+
+impl CalendarRef {
+    pub fn new(uid: &str, name: Option<&str>, addresses: Vec<String>) -> Self {
+        Self {
+            uid: uid.to_owned(),
+            name: name.map(|name| name.to_owned()),
+            addresses,
+        }
+    }
+}
+
+// The details of an event that is yet to be created on a calendar.
+#[derive(Debug)]
+pub struct NewEvent {
+    pub title: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+
+    pub starts: chrono::DateTime<chrono::Local>,
+    pub ends: chrono::DateTime<chrono::Local>,
+}
+
+impl NewEvent {
+    // Builds the VEVENT component of this event. The times are stored in UTC so that
+    // the component does not need to carry its own timezone definitions.
+    pub fn to_component(&self, uid: &str) -> icalendar::ICalendarComponent {
+        let mut component = icalendar::ICalendarComponent {
+            component_type: icalendar::ICalendarComponentType::VEvent,
+            ..Default::default()
+        };
+
+        component.add_uid(uid);
+        component.add_dtstamp(PartialDateTime::now());
+        component.add_property(
+            icalendar::ICalendarProperty::Dtstart,
+            utc_value(self.starts.to_utc()),
+        );
+        component.add_property(
+            icalendar::ICalendarProperty::Dtend,
+            utc_value(self.ends.to_utc()),
+        );
+        component.add_property(
+            icalendar::ICalendarProperty::Summary,
+            icalendar::ICalendarValue::Text(self.title.clone()),
+        );
+
+        if let Some(location) = &self.location {
+            component.add_property(
+                icalendar::ICalendarProperty::Location,
+                icalendar::ICalendarValue::Text(location.clone()),
+            );
+        }
+
+        if let Some(description) = &self.description {
+            component.add_property(
+                icalendar::ICalendarProperty::Description,
+                icalendar::ICalendarValue::Text(description.clone()),
+            );
+        }
+
+        component
+    }
+}
+
+// The changes to apply to an existing event. The fields that are not set are left
+// untouched on the event.
+#[derive(Debug, Default)]
+pub struct EventChanges {
+    pub title: Option<String>,
+    pub location: Option<String>,
+
+    pub starts: Option<chrono::DateTime<chrono::Local>>,
+    pub ends: Option<chrono::DateTime<chrono::Local>>,
+    pub duration: Option<chrono::Duration>,
+}
+
+impl EventChanges {
+    // Applies the changes on the component. If the event is moved without a new end,
+    // it keeps its original duration.
+    pub fn apply(&self, component: &mut icalendar::ICalendarComponent) -> anyhow::Result<()> {
+        if let Some(title) = &self.title {
+            set_text_property(component, icalendar::ICalendarProperty::Summary, title);
+        }
+
+        if let Some(location) = &self.location {
+            set_text_property(component, icalendar::ICalendarProperty::Location, location);
+        }
+
+        if self.starts.is_none() && self.ends.is_none() && self.duration.is_none() {
+            return Ok(());
+        }
+
+        let event = Event::from(&*component);
+        let (Some(starts), Some(ends)) = (event.starts, event.ends) else {
+            anyhow::bail!("Could not determine the current start and end of the event");
+        };
+
+        let new_starts = self.starts.map(|dt| dt.to_utc()).unwrap_or(starts.to_utc());
+        let new_ends = match (self.ends, self.duration) {
+            (Some(ends), _) => ends.to_utc(),
+            (None, Some(duration)) => new_starts + duration,
+            (None, None) => new_starts + (ends.to_utc() - starts.to_utc()),
+        };
+
+        if new_ends <= new_starts {
+            anyhow::bail!("The event should end after it starts");
+        }
+
+        set_dt_property(component, icalendar::ICalendarProperty::Dtstart, new_starts)?;
+
+        // The end is always stored as DTEND from here on.
+        component
+            .entries
+            .retain(|entry| entry.name != icalendar::ICalendarProperty::Duration);
+        set_dt_property(component, icalendar::ICalendarProperty::Dtend, new_ends)?;
+
+        Ok(())
+    }
+}
+
+// Returns a detached instance of a recurring event for the occurrence that starts at the
+// given time. The instance is identified by its RECURRENCE-ID, and, optionally, applies to
+// all the following occurrences too.
+pub fn detach_instance(
+    master: &icalendar::ICalendarComponent,
+    occurrence: chrono::DateTime<chrono::Utc>,
+    this_and_future: bool,
+) -> anyhow::Result<icalendar::ICalendarComponent> {
+    let event = Event::from(master);
+    let duration = match (event.starts, event.ends) {
+        (Some(starts), Some(ends)) => ends.to_utc() - starts.to_utc(),
+        _ => chrono::Duration::zero(),
+    };
+
+    let mut instance = master.clone();
+    instance.entries.retain(|entry| {
+        !matches!(
+            entry.name,
+            icalendar::ICalendarProperty::Rrule
+                | icalendar::ICalendarProperty::Rdate
+                | icalendar::ICalendarProperty::Exdate
+                | icalendar::ICalendarProperty::Exrule
+                | icalendar::ICalendarProperty::RecurrenceId
+        )
+    });
+
+    set_dt_property(
+        &mut instance,
+        icalendar::ICalendarProperty::Dtstart,
+        occurrence,
+    )?;
+    instance
+        .entries
+        .retain(|entry| entry.name != icalendar::ICalendarProperty::Duration);
+    set_dt_property(
+        &mut instance,
+        icalendar::ICalendarProperty::Dtend,
+        occurrence + duration,
+    )?;
+
+    let mut rid = dt_entry_like(
+        master
+            .property(&icalendar::ICalendarProperty::Dtstart)
+            .context("Recurring event does not have a start")?,
+        icalendar::ICalendarProperty::RecurrenceId,
+        occurrence,
+    );
+    if this_and_future {
+        rid.params.push(icalendar::ICalendarParameter::Range);
+    }
+    instance.entries.push(rid);
+
+    Ok(instance)
+}
+
+// Returns the RECURRENCE-ID, as the EDS formats it, of the occurrence of a recurring event
+// that starts at the given time.
+pub fn recurrence_id(
+    master: &icalendar::ICalendarComponent,
+    occurrence: chrono::DateTime<chrono::Utc>,
+) -> anyhow::Result<String> {
+    let entry = dt_entry_like(
+        master
+            .property(&icalendar::ICalendarProperty::Dtstart)
+            .context("Recurring event does not have a start")?,
+        icalendar::ICalendarProperty::RecurrenceId,
+        occurrence,
+    );
+
+    entry
+        .values
+        .first()
+        .and_then(|value| value.as_partial_date_time())
+        .map(format_dt_value)
+        .context("Could not format the recurrence id")
+}
+
+// Returns the RECURRENCE-ID of the component if it is a detached instance.
+pub fn component_recurrence_id(component: &icalendar::ICalendarComponent) -> Option<String> {
+    component
+        .property(&icalendar::ICalendarProperty::RecurrenceId)
+        .and_then(|it| it.values.first())
+        .and_then(|it| it.as_partial_date_time())
+        .map(format_dt_value)
+}
+
+// Formats a date or a date time value the way it is written in an ics file.
+pub fn format_dt_value(dt: &PartialDateTime) -> String {
+    let format = if dt.hour.is_some() {
+        icalendar::ICalendarValueType::DateTime
+    } else {
+        icalendar::ICalendarValueType::Date
+    };
+
+    let mut value = String::new();
+    let _ = dt.format_as_ical(&mut value, &format);
+    value
+}
+
+// Replaces a text property on the component.
+fn set_text_property(
+    component: &mut icalendar::ICalendarComponent,
+    property: icalendar::ICalendarProperty,
+    value: &str,
+) {
+    component.entries.retain(|entry| entry.name != property);
+    component.add_property(property, icalendar::ICalendarValue::Text(value.to_owned()));
+}
+
+// Replaces a date time property on the component while keeping its timezone. If the
+// property does not exist yet, the timezone of the start is used instead.
+fn set_dt_property(
+    component: &mut icalendar::ICalendarComponent,
+    property: icalendar::ICalendarProperty,
+    dt: chrono::DateTime<chrono::Utc>,
+) -> anyhow::Result<()> {
+    let template = component
+        .property(&property)
+        .or_else(|| component.property(&icalendar::ICalendarProperty::Dtstart))
+        .context("Could not determine the timezone of the event")?;
+
+    let entry = dt_entry_like(template, property.clone(), dt);
+    component.entries.retain(|entry| entry.name != property);
+    component.entries.push(entry);
+
+    Ok(())
+}
+
+// Builds a date time entry that uses the same timezone and value type as the template.
+fn dt_entry_like(
+    template: &icalendar::ICalendarEntry,
+    property: icalendar::ICalendarProperty,
+    dt: chrono::DateTime<chrono::Utc>,
+) -> icalendar::ICalendarEntry {
+    let tz = template
+        .params
+        .iter()
+        .find_map(|param| match param {
+            icalendar::ICalendarParameter::Tzid(tz) => Some(tz),
+            _ => None,
+        })
+        .and_then(|tz| {
+            crate::utils::normalize_timezone(tz)
+                .parse::<chrono_tz::Tz>()
+                .ok()
+        });
+
+    let original = template
+        .values
+        .first()
+        .and_then(|value| value.as_partial_date_time());
+    let is_date = original.is_some_and(|dt| dt.hour.is_none());
+    let is_utc = tz.is_none() && original.is_some_and(|dt| dt.tz_hour.is_some());
+
+    let naive = match tz {
+        Some(tz) => dt.with_timezone(&tz).naive_local(),
+        None if is_utc => dt.naive_utc(),
+        None => dt.with_timezone(&chrono::Local).naive_local(),
+    };
+
+    let value = PartialDateTime {
+        year: Some(naive.year() as u16),
+        month: Some(naive.month() as u8),
+        day: Some(naive.day() as u8),
+        hour: (!is_date).then_some(naive.hour() as u8),
+        minute: (!is_date).then_some(naive.minute() as u8),
+        second: (!is_date).then_some(naive.second() as u8),
+        tz_hour: is_utc.then_some(0),
+        tz_minute: is_utc.then_some(0),
+        tz_minus: false,
+    };
+
+    icalendar::ICalendarEntry {
+        name: property,
+        params: template
+            .params
+            .iter()
+            .filter(|param| {
+                matches!(
+                    param,
+                    icalendar::ICalendarParameter::Tzid(_)
+                        | icalendar::ICalendarParameter::Value(_)
+                )
+            })
+            .cloned()
+            .collect(),
+        values: vec![icalendar::ICalendarValue::PartialDateTime(Box::new(value))],
+    }
+}
+
+// Returns the date time value of a UTC time.
+fn utc_value(dt: chrono::DateTime<chrono::Utc>) -> icalendar::ICalendarValue {
+    icalendar::ICalendarValue::PartialDateTime(Box::new(PartialDateTime::from_utc_timestamp(
+        dt.timestamp(),
+    )))
 }
 
 // Load up a property from the calendar component as a string value.
-fn str_property(
+pub(super) fn str_property(
     component: &icalendar::ICalendarComponent,
     property: &icalendar::ICalendarProperty,
 ) -> Option<String> {
@@ -79,9 +632,56 @@ fn str_property(
     )
 }
 
+// Returns the end of an event without a DTEND from its DURATION. Without a duration
+// either, the events that start on a date take up that day, and the ones that start at a
+// time end right then, as RFC 5545 says.
+fn implicit_end(
+    component: &icalendar::ICalendarComponent,
+    starts: chrono::DateTime<rrule::Tz>,
+    all_day: bool,
+) -> Option<chrono::DateTime<rrule::Tz>> {
+    let Some(entry) = component.property(&icalendar::ICalendarProperty::Duration) else {
+        return match all_day {
+            true => starts.checked_add_days(chrono::Days::new(1)),
+            false => Some(starts),
+        };
+    };
+
+    let icalendar::ICalendarValue::Duration(duration) = entry.values.first()? else {
+        return None;
+    };
+
+    if duration.neg {
+        return None;
+    }
+
+    // The weeks and the days keep the time of the day, even when the clocks change in
+    // between, while the rest of the duration is exact.
+    let days = duration.weeks as u64 * 7 + duration.days as u64;
+    let time = chrono::TimeDelta::seconds(
+        duration.hours as i64 * 3600 + duration.minutes as i64 * 60 + duration.seconds as i64,
+    );
+
+    starts
+        .checked_add_days(chrono::Days::new(days))?
+        .checked_add_signed(time)
+}
+
+// Returns whether the property holds a date, rather than a date time.
+pub(super) fn is_date_property(
+    component: &icalendar::ICalendarComponent,
+    property: &icalendar::ICalendarProperty,
+) -> bool {
+    component
+        .property(property)
+        .and_then(|property| property.values.first())
+        .and_then(|value| value.as_partial_date_time())
+        .is_some_and(|value| value.hour.is_none())
+}
+
 // Transform the date time value from the calendar component while taking the
 // timezone into account.
-fn dt_property(
+pub(super) fn dt_property(
     component: &icalendar::ICalendarComponent,
     property: &icalendar::ICalendarProperty,
 ) -> Option<anyhow::Result<chrono::DateTime<rrule::Tz>>> {
@@ -89,23 +689,23 @@ fn dt_property(
 
     // NaiveDateTime
     let now = chrono::Local::now();
-    let dt = property.values.first()?.as_partial_date_time()?;
+    let value = property.values.first()?.as_partial_date_time()?;
     let dt = chrono::NaiveDate::from_ymd_opt(
-        dt.year.map(|y| y as i32).unwrap_or(now.year()),
-        dt.month.map(|m| m as u32).unwrap_or(now.month()),
-        dt.day.map(|d| d as u32).unwrap_or(now.day()),
+        value.year.map(|y| y as i32).unwrap_or(now.year()),
+        value.month.map(|m| m as u32).unwrap_or(now.month()),
+        value.day.map(|d| d as u32).unwrap_or(now.day()),
     )?
     .and_time(chrono::NaiveTime::from_hms_opt(
-        dt.hour.map(|h| h as u32).unwrap_or_default(),
-        dt.minute.map(|m| m as u32).unwrap_or_default(),
-        dt.second.map(|s| s as u32).unwrap_or_default(),
+        value.hour.map(|h| h as u32).unwrap_or_default(),
+        value.minute.map(|m| m as u32).unwrap_or_default(),
+        value.second.map(|s| s as u32).unwrap_or_default(),
     )?);
 
     // Timezone
     let tz = property
         .params
-        .first()
-        .and_then(|value| match value {
+        .iter()
+        .find_map(|value| match value {
             icalendar::ICalendarParameter::Tzid(tz) => Some(tz),
             _ => None,
         })
@@ -120,13 +720,241 @@ fn dt_property(
             Ok(tz) => rrule::Tz::Tz(tz)
                 .from_local_datetime(&dt)
                 .earliest()
-                .and_then(|dt| Some(Ok(dt))),
+                .map(Ok),
             Err(err) => Some(Err(err)),
         }
+    } else if value.tz_hour.is_some() {
+        // Values that end with a "Z" are in UTC.
+        rrule::Tz::Tz(chrono_tz::UTC)
+            .from_local_datetime(&dt)
+            .earliest()
+            .map(Ok)
     } else {
         rrule::Tz::Local(chrono::Local)
             .from_local_datetime(&dt)
             .earliest()
-            .and_then(|dt| Some(Ok(dt)))
+            .map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn master(dtstart: &str, dtend: &str) -> icalendar::ICalendarComponent {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:standup\r\n{}\r\n{}\r\n\
+             RRULE:FREQ=DAILY\r\nEXDATE:20250110T100000Z\r\nSUMMARY:Standup\r\n\
+             END:VEVENT\r\nEND:VCALENDAR\r\n",
+            dtstart, dtend,
+        );
+        let mut trees = crate::eds::calendar::parse_components(
+            &ics,
+            &icalendar::ICalendarComponentType::VEvent,
+        );
+
+        trees.remove(0).components.remove(0)
+    }
+
+    fn utc(dt: &str) -> chrono::DateTime<chrono::Utc> {
+        dt.parse().unwrap()
+    }
+
+    // Returns the end of an event that starts at the DTSTART given, without a DTEND.
+    fn end_of(properties: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:event\r\n{}END:VEVENT\r\nEND:VCALENDAR\r\n",
+            properties.replace('\n', "\r\n"),
+        );
+        let trees = crate::eds::calendar::parse_components(
+            &ics,
+            &icalendar::ICalendarComponentType::VEvent,
+        );
+        let component = &trees[0].components[0];
+
+        let starts = dt_property(component, &icalendar::ICalendarProperty::Dtstart)?.unwrap();
+        let all_day = is_date_property(component, &icalendar::ICalendarProperty::Dtstart);
+
+        implicit_end(component, starts, all_day).map(|ends| ends.to_utc())
+    }
+
+    // Returns the value of a date time property, and its timezone.
+    fn dt(
+        component: &icalendar::ICalendarComponent,
+        property: icalendar::ICalendarProperty,
+    ) -> (String, Option<String>) {
+        let entry = component.property(&property).unwrap();
+        let tz = entry.params.iter().find_map(|param| match param {
+            icalendar::ICalendarParameter::Tzid(tz) => Some(tz.to_owned()),
+            _ => None,
+        });
+
+        (
+            format_dt_value(entry.values[0].as_partial_date_time().unwrap()),
+            tz,
+        )
+    }
+
+    fn assert_detached(instance: &icalendar::ICalendarComponent) {
+        for property in [
+            icalendar::ICalendarProperty::Rrule,
+            icalendar::ICalendarProperty::Exdate,
+        ] {
+            assert!(instance.property(&property).is_none(), "{:?}", property);
+        }
+        assert_eq!(
+            str_property(instance, &icalendar::ICalendarProperty::Uid).as_deref(),
+            Some("standup")
+        );
+    }
+
+    #[test]
+    fn identifies_the_occurrences_in_the_timezone_of_the_series() {
+        let master = master(
+            "DTSTART;TZID=Europe/Paris:20250106T100000",
+            "DTEND;TZID=Europe/Paris:20250106T103000",
+        );
+        let occurrence = utc("2025-01-08T09:00:00Z");
+
+        assert_eq!(
+            recurrence_id(&master, occurrence).unwrap(),
+            "20250108T100000"
+        );
+
+        let instance = detach_instance(&master, occurrence, false).unwrap();
+        assert_detached(&instance);
+
+        let paris = Some("Europe/Paris".to_owned());
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::Dtstart),
+            ("20250108T100000".to_owned(), paris.clone()),
+        );
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::Dtend),
+            ("20250108T103000".to_owned(), paris.clone()),
+        );
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::RecurrenceId),
+            ("20250108T100000".to_owned(), paris),
+        );
+    }
+
+    #[test]
+    fn identifies_the_occurrences_of_the_series_in_utc() {
+        let master = master("DTSTART:20250106T100000Z", "DTEND:20250106T103000Z");
+        let occurrence = utc("2025-01-08T10:00:00Z");
+
+        assert_eq!(
+            recurrence_id(&master, occurrence).unwrap(),
+            "20250108T100000Z"
+        );
+
+        let instance = detach_instance(&master, occurrence, false).unwrap();
+        assert_detached(&instance);
+
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::Dtstart),
+            ("20250108T100000Z".to_owned(), None),
+        );
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::Dtend),
+            ("20250108T103000Z".to_owned(), None),
+        );
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::RecurrenceId),
+            ("20250108T100000Z".to_owned(), None),
+        );
+    }
+
+    #[test]
+    fn identifies_the_occurrences_of_the_series_on_dates() {
+        let master = master("DTSTART;VALUE=DATE:20250106", "DTEND;VALUE=DATE:20250107");
+        let occurrence = chrono::Local
+            .with_ymd_and_hms(2025, 1, 8, 0, 0, 0)
+            .unwrap()
+            .to_utc();
+
+        assert_eq!(recurrence_id(&master, occurrence).unwrap(), "20250108");
+
+        let instance = detach_instance(&master, occurrence, false).unwrap();
+        assert_detached(&instance);
+
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::Dtstart),
+            ("20250108".to_owned(), None),
+        );
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::Dtend),
+            ("20250109".to_owned(), None),
+        );
+        assert_eq!(
+            dt(&instance, icalendar::ICalendarProperty::RecurrenceId),
+            ("20250108".to_owned(), None),
+        );
+    }
+
+    #[test]
+    fn marks_the_instances_that_apply_to_the_following_occurrences() {
+        let master = master("DTSTART:20250106T100000Z", "DTEND:20250106T103000Z");
+
+        let instance = detach_instance(&master, utc("2025-01-08T10:00:00Z"), true).unwrap();
+        let rid = instance
+            .property(&icalendar::ICalendarProperty::RecurrenceId)
+            .unwrap();
+        assert!(rid.params.contains(&icalendar::ICalendarParameter::Range));
+
+        let instance = detach_instance(&master, utc("2025-01-08T10:00:00Z"), false).unwrap();
+        let rid = instance
+            .property(&icalendar::ICalendarProperty::RecurrenceId)
+            .unwrap();
+        assert!(!rid.params.contains(&icalendar::ICalendarParameter::Range));
+    }
+
+    #[test]
+    fn ends_the_events_after_their_duration() {
+        assert_eq!(
+            end_of("DTSTART:20250106T100000Z\nDURATION:PT1H30M\n"),
+            Some(utc("2025-01-06T11:30:00Z")),
+        );
+        assert_eq!(
+            end_of("DTSTART:20250106T100000Z\nDURATION:P1W2DT1H\n"),
+            Some(utc("2025-01-15T11:00:00Z")),
+        );
+    }
+
+    #[test]
+    fn keeps_the_time_of_the_day_over_the_days_the_clocks_change() {
+        // The clocks go forward on March 30 in Berlin, so, that day is an hour short.
+        assert_eq!(
+            end_of("DTSTART;TZID=Europe/Berlin:20250329T100000\nDURATION:P1D\n"),
+            Some(utc("2025-03-30T08:00:00Z")),
+        );
+        assert_eq!(
+            end_of("DTSTART;TZID=Europe/Berlin:20250329T100000\nDURATION:PT24H\n"),
+            Some(utc("2025-03-30T09:00:00Z")),
+        );
+    }
+
+    #[test]
+    fn ends_the_events_without_a_duration_as_they_start() {
+        let starts = chrono::Local
+            .with_ymd_and_hms(2025, 1, 6, 0, 0, 0)
+            .unwrap()
+            .to_utc();
+
+        // The ones on a date take up the whole day.
+        assert_eq!(
+            end_of("DTSTART;VALUE=DATE:20250106\n"),
+            Some(starts + chrono::TimeDelta::days(1)),
+        );
+        assert_eq!(
+            end_of("DTSTART:20250106T100000Z\n"),
+            Some(utc("2025-01-06T10:00:00Z")),
+        );
+    }
+
+    #[test]
+    fn does_not_end_the_events_with_a_negative_duration() {
+        assert_eq!(end_of("DTSTART:20250106T100000Z\nDURATION:-PT1H\n"), None);
     }
 }
@@ -1,6 +1,10 @@
 use std::collections::HashMap;
 use zbus::zvariant;
 
+// The object paths mapped to their interfaces and the properties on them.
+pub type ManagedObjects =
+    HashMap<zvariant::OwnedObjectPath, HashMap<String, HashMap<String, zvariant::OwnedValue>>>;
+
 #[zbus::proxy(
     default_service = "org.gnome.evolution.dataserver.Sources5",
     default_path = "/org/gnome/evolution/dataserver/SourceManager",
@@ -9,11 +13,7 @@ use zbus::zvariant;
 pub trait Sources {
     // Returns the source objects that are created and managed at runtime.
     // Uses the standard org.freedesktop.DBus.ObjectManager.
-    async fn get_managed_objects(
-        &self,
-    ) -> zbus::Result<
-        HashMap<zvariant::OwnedObjectPath, HashMap<String, HashMap<String, zvariant::OwnedValue>>>,
-    >;
+    async fn get_managed_objects(&self) -> zbus::Result<ManagedObjects>;
 }
 
 #[zbus::proxy(
@@ -26,6 +26,12 @@ pub trait CalendarFactory {
     // calendar using its UID. Ideally, the return type should be os, but it is
     // reported as ss during introspection.
     async fn open_calendar(&self, uid: &str) -> zbus::Result<(String, String)>;
+
+    // Same as open_calendar, but, for task lists.
+    async fn open_task_list(&self, uid: &str) -> zbus::Result<(String, String)>;
+
+    // Same as open_calendar, but, for memo lists.
+    async fn open_memo_list(&self, uid: &str) -> zbus::Result<(String, String)>;
 }
 
 #[zbus::proxy(
@@ -33,7 +39,73 @@ pub trait CalendarFactory {
     interface = "org.gnome.evolution.dataserver.Calendar"
 )]
 pub trait Calendar {
+    // Whether the objects on the calendar can be changed.
+    #[zbus(property)]
+    fn writable(&self) -> zbus::Result<bool>;
+
     // This call returns ics_objects based on a query string. The object path
     // to query should be based on the calendar.
     async fn get_object_list(&self, q: &str) -> zbus::Result<Vec<String>>;
+
+    // This call creates a live view over the objects matching the query string
+    // and returns its object path. Like open_calendar, it is reported as s
+    // during introspection instead of o.
+    async fn get_view(&self, q: &str) -> zbus::Result<String>;
+
+    // This call stores new ics_objects on the calendar and returns their UIDs.
+    // The flags are the E_CAL_OPERATION_FLAG_* values.
+    async fn create_objects(&self, ics_objects: &[&str], opflags: u32)
+    -> zbus::Result<Vec<String>>;
+
+    // This call returns the ics_object of an object by its UID and RID. If the object
+    // has detached instances, all of them are returned wrapped in a VCALENDAR.
+    async fn get_object(&self, uid: &str, rid: &str) -> zbus::Result<String>;
+
+    // This call replaces existing ics_objects on the calendar. The mod_type is the nick of
+    // an E_CAL_OBJ_MOD_* value, which decides the affected instances of recurring objects.
+    async fn modify_objects(
+        &self,
+        ics_objects: &[&str],
+        mod_type: &str,
+        opflags: u32,
+    ) -> zbus::Result<()>;
+
+    // This call removes the objects identified by their UID and RID pairs. The RID is
+    // empty when the object is not a specific instance of a recurring object.
+    async fn remove_objects(
+        &self,
+        uid_rid_array: &[(&str, &str)],
+        mod_type: &str,
+        opflags: u32,
+    ) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    default_service = "org.gnome.evolution.dataserver.Calendar8",
+    interface = "org.gnome.evolution.dataserver.CalendarView"
+)]
+pub trait CalendarView {
+    // Starts emitting the signals on this view. The initial set of objects is
+    // reported through ObjectsAdded, followed by Complete.
+    async fn start(&self) -> zbus::Result<()>;
+
+    // Releases the view on the server side.
+    async fn dispose(&self) -> zbus::Result<()>;
+
+    // Emitted with the ics_objects that started matching the query.
+    #[zbus(signal)]
+    fn objects_added(&self, objects: Vec<String>) -> zbus::Result<()>;
+
+    // Emitted with the ics_objects that were changed and still match the query.
+    #[zbus(signal)]
+    fn objects_modified(&self, objects: Vec<String>) -> zbus::Result<()>;
+
+    // Emitted with the ids of the objects that stopped matching the query.
+    // Every id is formatted as "<uid>[\n<rid>]".
+    #[zbus(signal)]
+    fn objects_removed(&self, uids: Vec<String>) -> zbus::Result<()>;
+
+    // Emitted once the initial set of objects has been reported.
+    #[zbus(signal)]
+    fn complete(&self, error: Vec<String>) -> zbus::Result<()>;
 }
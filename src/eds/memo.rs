@@ -0,0 +1,56 @@
+use calcard::icalendar;
+
+use crate::eds::event::{dt_property, str_property};
+
+#[derive(Debug)]
+pub struct Memo {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub categories: Vec<String>,
+
+    // The day, or the time, the memo is attached to.
+    pub starts: Option<chrono::DateTime<rrule::Tz>>,
+}
+
+impl From<&icalendar::ICalendarComponent> for Memo {
+    fn from(component: &icalendar::ICalendarComponent) -> Self {
+        // Journal entries are allowed to have more than one description.
+        let descriptions: Vec<&str> = component
+            .properties(&icalendar::ICalendarProperty::Description)
+            .filter_map(|entry| entry.values.first()?.as_text())
+            .collect();
+
+        Self {
+            title: str_property(component, &icalendar::ICalendarProperty::Summary),
+            description: match descriptions.is_empty() {
+                true => None,
+                false => Some(descriptions.join("\n")),
+            },
+            categories: component
+                .properties(&icalendar::ICalendarProperty::Categories)
+                .flat_map(|entry| entry.values.iter())
+                .filter_map(|value| value.as_text())
+                .map(|category| category.to_owned())
+                .collect(),
+
+            starts: match dt_property(component, &icalendar::ICalendarProperty::Dtstart) {
+                Some(Ok(dtstarts)) => Some(dtstarts),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Memo {
+    // Returns whether the text appears on the title, the description or the categories
+    // of the memo, ignoring the case.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+
+        self.title
+            .iter()
+            .chain(self.description.iter())
+            .chain(self.categories.iter())
+            .any(|value| value.to_lowercase().contains(&text))
+    }
+}
@@ -1,3 +1,9 @@
+pub mod alarm;
+pub mod backend;
 pub mod calendar;
 pub mod event;
 mod ipc;
+pub mod memo;
+pub mod query;
+pub mod status;
+pub mod task;
@@ -0,0 +1,427 @@
+use calcard::icalendar;
+
+// The fields of a component that can be searched with a text query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Field {
+    /// Any of the text fields.
+    Any,
+    /// The title.
+    Summary,
+    /// The description.
+    Description,
+    /// The location.
+    Location,
+    /// The names and the addresses of the attendees.
+    Attendee,
+    /// The name and the address of the organizer.
+    Organizer,
+    /// The comments.
+    Comment,
+}
+
+impl Field {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Field::Any => "any",
+            Field::Summary => "summary",
+            Field::Description => "description",
+            Field::Location => "location",
+            Field::Attendee => "attendee",
+            Field::Organizer => "organizer",
+            Field::Comment => "comment",
+        }
+    }
+}
+
+// A query that picks the components on the EDS, so that they can be filtered there
+// instead of being pulled and filtered here. It is turned into the S-expression the
+// EDS expects with its Display. Not all the predicates are used by the commands yet.
+#[derive(Debug, Clone)]
+pub enum Query {
+    // Matches every component.
+    All,
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    // Matches the components with the text in the field, ignoring the case.
+    Contains(Field, String),
+    // Matches the components with all of the categories.
+    HasCategories(Vec<String>),
+    // Matches the components with an alarm going off in the range.
+    HasAlarmsInRange(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>),
+    // Matches the component with the UID.
+    Uid(String),
+    // Matches the components with an occurrence in the range.
+    OccurInTimeRange(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>),
+}
+
+impl Query {
+    pub fn contains(field: Field, text: &str) -> Self {
+        Self::Contains(field, text.to_owned())
+    }
+
+    #[allow(dead_code)]
+    pub fn has_categories<S: AsRef<str>>(categories: &[S]) -> Self {
+        Self::HasCategories(
+            categories
+                .iter()
+                .map(|category| category.as_ref().to_owned())
+                .collect(),
+        )
+    }
+
+    pub fn has_alarms_in_range<Tz: chrono::TimeZone>(
+        starts: chrono::DateTime<Tz>,
+        ends: chrono::DateTime<Tz>,
+    ) -> Self {
+        Self::HasAlarmsInRange(starts.to_utc(), ends.to_utc())
+    }
+
+    #[allow(dead_code)]
+    pub fn uid(uid: &str) -> Self {
+        Self::Uid(uid.to_owned())
+    }
+
+    pub fn occur_in_time_range<Tz: chrono::TimeZone>(
+        starts: chrono::DateTime<Tz>,
+        ends: chrono::DateTime<Tz>,
+    ) -> Self {
+        Self::OccurInTimeRange(starts.to_utc(), ends.to_utc())
+    }
+
+    // Returns a query that matches the components matched by both of the queries.
+    pub fn and(self, other: Query) -> Self {
+        match (self, other) {
+            (Query::All, query) | (query, Query::All) => query,
+            (Query::And(mut queries), Query::And(others)) => {
+                queries.extend(others);
+                Query::And(queries)
+            }
+            (Query::And(mut queries), query) => {
+                queries.push(query);
+                Query::And(queries)
+            }
+            (query, other) => Query::And(vec![query, other]),
+        }
+    }
+
+    // Returns a query that matches the components matched by either of the queries.
+    pub fn or(self, other: Query) -> Self {
+        match (self, other) {
+            (Query::All, _) | (_, Query::All) => Query::All,
+            (Query::Or(mut queries), Query::Or(others)) => {
+                queries.extend(others);
+                Query::Or(queries)
+            }
+            (Query::Or(mut queries), query) => {
+                queries.push(query);
+                Query::Or(queries)
+            }
+            (query, other) => Query::Or(vec![query, other]),
+        }
+    }
+}
+
+impl Query {
+    // Returns whether the component matches the query, for the backends that cannot run
+    // the queries themselves. The time ranges are left to the expansion of the events,
+    // so, they only rule out the components that are known not to match.
+    pub fn matches(&self, component: &icalendar::ICalendarComponent) -> bool {
+        self.evaluate(component).unwrap_or(true)
+    }
+
+    // Returns whether the component matches the query, or nothing when it cannot be told
+    // without expanding the component.
+    fn evaluate(&self, component: &icalendar::ICalendarComponent) -> Option<bool> {
+        match self {
+            Query::All => Some(true),
+            Query::And(queries) => queries
+                .iter()
+                .try_fold(Some(true), |all, query| match query.evaluate(component) {
+                    Some(false) => Err(()),
+                    Some(true) => Ok(all),
+                    None => Ok(None),
+                })
+                .unwrap_or(Some(false)),
+            Query::Or(queries) => queries
+                .iter()
+                .try_fold(Some(false), |any, query| match query.evaluate(component) {
+                    Some(true) => Err(()),
+                    Some(false) => Ok(any),
+                    None => Ok(None),
+                })
+                .unwrap_or(Some(true)),
+            Query::Not(query) => query.evaluate(component).map(|matches| !matches),
+            Query::Contains(field, text) => {
+                let text = text.to_lowercase();
+                Some(
+                    field_texts(component, *field)
+                        .any(|value| value.to_lowercase().contains(&text)),
+                )
+            }
+            Query::HasCategories(categories) => Some(categories.iter().all(|category| {
+                field_values(component, &icalendar::ICalendarProperty::Categories)
+                    .any(|value| value.eq_ignore_ascii_case(category))
+            })),
+            Query::HasAlarmsInRange(_, _) => match component.component_ids.is_empty() {
+                true => Some(false),
+                false => None,
+            },
+            Query::Uid(uid) => Some(component.uid() == Some(uid.as_str())),
+            Query::OccurInTimeRange(_, _) => None,
+        }
+    }
+}
+
+impl std::ops::Not for Query {
+    type Output = Query;
+
+    fn not(self) -> Query {
+        match self {
+            Query::Not(query) => *query,
+            query => Query::Not(Box::new(query)),
+        }
+    }
+}
+
+impl std::fmt::Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Query::All => write!(f, "#t"),
+            // The EDS does not take an empty "and" or "or", so, they are written out as
+            // the values they stand for.
+            Query::And(queries) if queries.is_empty() => write!(f, "#t"),
+            Query::Or(queries) if queries.is_empty() => write!(f, "#f"),
+            Query::And(queries) => write_list(f, "and", queries),
+            Query::Or(queries) => write_list(f, "or", queries),
+            Query::Not(query) => write!(f, "(not {})", query),
+            Query::Contains(field, text) => {
+                write!(f, "(contains? \"{}\" {})", field.as_str(), quote(text))
+            }
+            Query::HasCategories(categories) => {
+                write!(f, "(has-categories?")?;
+                for category in categories {
+                    write!(f, " {}", quote(category))?;
+                }
+                write!(f, ")")
+            }
+            Query::HasAlarmsInRange(starts, ends) => write!(
+                f,
+                "(has-alarms-in-range? {} {})",
+                make_time(starts),
+                make_time(ends)
+            ),
+            Query::Uid(uid) => write!(f, "(uid? {})", quote(uid)),
+            Query::OccurInTimeRange(starts, ends) => write!(
+                f,
+                "(occur-in-time-range? {} {})",
+                make_time(starts),
+                make_time(ends)
+            ),
+        }
+    }
+}
+
+fn write_list(f: &mut std::fmt::Formatter<'_>, op: &str, queries: &[Query]) -> std::fmt::Result {
+    write!(f, "({}", op)?;
+    for query in queries {
+        write!(f, " {}", query)?;
+    }
+    write!(f, ")")
+}
+
+// Returns the text as a string literal, with the quotes and the backslashes in it escaped.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Returns the time in the form the EDS expects it in. The time is given in UTC, so that
+// the EDS does not have to guess its timezone.
+fn make_time(dt: &chrono::DateTime<chrono::Utc>) -> String {
+    format!("(make-time \"{}\")", dt.format("%Y%m%dT%H%M%SZ"))
+}
+
+// Returns the text values of the field on the component, along with the names of the
+// attendees and the organizer.
+fn field_texts(
+    component: &icalendar::ICalendarComponent,
+    field: Field,
+) -> Box<dyn Iterator<Item = &str> + '_> {
+    let property = match field {
+        Field::Any => {
+            return Box::new(
+                [
+                    Field::Summary,
+                    Field::Description,
+                    Field::Location,
+                    Field::Attendee,
+                    Field::Organizer,
+                    Field::Comment,
+                ]
+                .into_iter()
+                .flat_map(|field| field_texts(component, field)),
+            );
+        }
+        Field::Summary => icalendar::ICalendarProperty::Summary,
+        Field::Description => icalendar::ICalendarProperty::Description,
+        Field::Location => icalendar::ICalendarProperty::Location,
+        Field::Attendee => icalendar::ICalendarProperty::Attendee,
+        Field::Organizer => icalendar::ICalendarProperty::Organizer,
+        Field::Comment => icalendar::ICalendarProperty::Comment,
+    };
+
+    Box::new(
+        component
+            .entries
+            .iter()
+            .filter(move |entry| entry.name == property)
+            .flat_map(|entry| {
+                let names = entry.params.iter().filter_map(|param| match param {
+                    icalendar::ICalendarParameter::Cn(name) => Some(name.as_str()),
+                    _ => None,
+                });
+
+                entry
+                    .values
+                    .iter()
+                    .filter_map(|value| value.as_text())
+                    .chain(names)
+            }),
+    )
+}
+
+fn field_values<'c>(
+    component: &'c icalendar::ICalendarComponent,
+    property: &'c icalendar::ICalendarProperty,
+) -> impl Iterator<Item = &'c str> {
+    component
+        .properties(property)
+        .flat_map(|entry| entry.values.iter().filter_map(|value| value.as_text()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(dt: &str) -> chrono::DateTime<chrono::Utc> {
+        dt.parse().unwrap()
+    }
+
+    #[test]
+    fn escapes_the_quotes_and_the_backslashes() {
+        let query = Query::contains(Field::Summary, r#"Say "hi" \ wave"#);
+        assert_eq!(
+            query.to_string(),
+            r#"(contains? "summary" "Say \"hi\" \\ wave")"#
+        );
+    }
+
+    #[test]
+    fn escapes_the_uids_and_the_categories() {
+        assert_eq!(
+            Query::uid(r#"event-"1"\"#).to_string(),
+            r#"(uid? "event-\"1\"\\")"#
+        );
+        assert_eq!(
+            Query::has_categories(&["Work", r#"On "call""#]).to_string(),
+            r#"(has-categories? "Work" "On \"call\"")"#
+        );
+    }
+
+    #[test]
+    fn negates_the_queries() {
+        let query = !Query::contains(Field::Summary, "standup");
+        assert_eq!(
+            query.to_string(),
+            r#"(not (contains? "summary" "standup"))"#
+        );
+
+        // Negating twice gives the query back instead of nesting it.
+        assert_eq!((!query).to_string(), r#"(contains? "summary" "standup")"#);
+
+        let query = !Query::uid("standup").or(Query::has_categories(&["Work"]));
+        assert_eq!(
+            query.to_string(),
+            r#"(not (or (uid? "standup") (has-categories? "Work")))"#
+        );
+    }
+
+    #[test]
+    fn matches_the_uids_the_categories_and_their_negations() {
+        let trees = crate::eds::calendar::parse_components(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:standup\r\nSUMMARY:Standup\r\n\
+             CATEGORIES:Work,Daily\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            &icalendar::ICalendarComponentType::VEvent,
+        );
+        let component = &trees[0].components[0];
+
+        assert!(Query::uid("standup").matches(component));
+        assert!(!Query::uid("review").matches(component));
+        assert!(Query::has_categories(&["work", "Daily"]).matches(component));
+        assert!(!Query::has_categories(&["Work", "Personal"]).matches(component));
+        assert!((!Query::uid("review")).matches(component));
+        assert!(!(!Query::has_categories(&["Work"])).matches(component));
+    }
+
+    #[test]
+    fn writes_the_times_in_utc() {
+        let starts = chrono::DateTime::parse_from_rfc3339("2025-01-06T10:00:00+02:00").unwrap();
+        let ends = chrono::DateTime::parse_from_rfc3339("2025-01-07T00:00:05-05:30").unwrap();
+
+        assert_eq!(
+            Query::occur_in_time_range(starts, ends).to_string(),
+            "(occur-in-time-range? (make-time \"20250106T080000Z\") (make-time \"20250107T053005Z\"))",
+        );
+        assert_eq!(
+            Query::has_alarms_in_range(starts, ends).to_string(),
+            "(has-alarms-in-range? (make-time \"20250106T080000Z\") (make-time \"20250107T053005Z\"))",
+        );
+    }
+
+    #[test]
+    fn nests_the_ands_and_the_ors() {
+        let query =
+            Query::occur_in_time_range(utc("2025-01-06T00:00:00Z"), utc("2025-01-07T00:00:00Z"))
+                .and(
+                    Query::contains(Field::Summary, "standup")
+                        .or(Query::contains(Field::Location, "room"))
+                        .or(Query::contains(Field::Attendee, "ana")),
+                )
+                .and(Query::contains(Field::Any, "daily"));
+
+        assert_eq!(
+            query.to_string(),
+            "(and (occur-in-time-range? (make-time \"20250106T000000Z\") (make-time \"20250107T000000Z\")) \
+             (or (contains? \"summary\" \"standup\") (contains? \"location\" \"room\") (contains? \"attendee\" \"ana\")) \
+             (contains? \"any\" \"daily\"))",
+        );
+    }
+
+    #[test]
+    fn folds_the_queries_that_match_everything() {
+        let query = Query::contains(Field::Summary, "standup");
+
+        assert_eq!(Query::All.and(query.clone()).to_string(), query.to_string());
+        assert_eq!(query.clone().and(Query::All).to_string(), query.to_string());
+        assert_eq!(query.or(Query::All).to_string(), "#t");
+    }
+
+    #[test]
+    fn writes_out_the_empty_ands_and_ors() {
+        assert_eq!(Query::And(vec![]).to_string(), "#t");
+        assert_eq!(Query::Or(vec![]).to_string(), "#f");
+        assert_eq!(
+            Query::And(vec![Query::Or(vec![]), Query::All]).to_string(),
+            "(and #f #t)"
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use calcard::icalendar;
+
+// The statuses the events and the tasks can be picked by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Status {
+    /// The events that are not confirmed yet.
+    Tentative,
+    /// The confirmed events, and the events without a status.
+    Confirmed,
+    /// The cancelled events and tasks.
+    Cancelled,
+    /// The tasks that still need to be done, and the tasks without a status.
+    NeedsAction,
+    /// The tasks that are being worked on.
+    InProcess,
+    /// The completed tasks.
+    Completed,
+}
+
+impl Status {
+    // Returns the status of a component, or, the given one when the component does not
+    // have any. The statuses that cannot be picked, like the ones of the memos, are
+    // returned as nothing.
+    pub fn of(status: Option<&icalendar::ICalendarStatus>, missing: Status) -> Option<Self> {
+        match status {
+            None => Some(missing),
+            Some(icalendar::ICalendarStatus::Tentative) => Some(Status::Tentative),
+            Some(icalendar::ICalendarStatus::Confirmed) => Some(Status::Confirmed),
+            Some(icalendar::ICalendarStatus::Cancelled) => Some(Status::Cancelled),
+            Some(icalendar::ICalendarStatus::NeedsAction) => Some(Status::NeedsAction),
+            Some(icalendar::ICalendarStatus::InProcess) => Some(Status::InProcess),
+            Some(icalendar::ICalendarStatus::Completed) => Some(Status::Completed),
+            Some(_) => None,
+        }
+    }
+}
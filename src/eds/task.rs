@@ -0,0 +1,77 @@
+use calcard::icalendar;
+
+use crate::eds::event::{dt_property, str_property};
+
+#[derive(Debug)]
+pub struct Task {
+    pub status: Option<icalendar::ICalendarStatus>,
+
+    pub title: Option<String>,
+
+    pub due: Option<chrono::DateTime<rrule::Tz>>,
+    pub completed: Option<chrono::DateTime<rrule::Tz>>,
+
+    // Ranges from 1, the highest, to 9, the lowest. Zero means it is undefined.
+    pub priority: Option<u8>,
+    pub percent_complete: Option<u8>,
+}
+
+impl From<&icalendar::ICalendarComponent> for Task {
+    fn from(component: &icalendar::ICalendarComponent) -> Self {
+        Self {
+            status: component.status().cloned(),
+
+            title: str_property(component, &icalendar::ICalendarProperty::Summary),
+
+            due: match dt_property(component, &icalendar::ICalendarProperty::Due) {
+                Some(Ok(due)) => Some(due),
+                _ => None,
+            },
+            completed: match dt_property(component, &icalendar::ICalendarProperty::Completed) {
+                Some(Ok(completed)) => Some(completed),
+                _ => None,
+            },
+
+            priority: int_property(component, &icalendar::ICalendarProperty::Priority),
+            percent_complete: int_property(
+                component,
+                &icalendar::ICalendarProperty::PercentComplete,
+            ),
+        }
+    }
+}
+
+impl Task {
+    // Returns whether the task still needs to be done.
+    pub fn is_open(&self) -> bool {
+        !matches!(
+            self.status,
+            Some(icalendar::ICalendarStatus::Completed | icalendar::ICalendarStatus::Cancelled)
+        ) && self.completed.is_none()
+            && self.percent_complete.unwrap_or_default() < 100
+    }
+
+    // Returns whether the task is still open past its due time.
+    pub fn is_overdue(&self, now: chrono::DateTime<chrono::Local>) -> bool {
+        self.is_open() && self.due.is_some_and(|due| due.to_utc() < now.to_utc())
+    }
+
+    // Returns whether the task is due on the given day.
+    pub fn is_due_on(&self, day: chrono::NaiveDate) -> bool {
+        self.due
+            .is_some_and(|due| due.with_timezone(&chrono::Local).date_naive() == day)
+    }
+}
+
+// Load up a property from the calendar component as a small integer value.
+fn int_property(
+    component: &icalendar::ICalendarComponent,
+    property: &icalendar::ICalendarProperty,
+) -> Option<u8> {
+    component
+        .property(property)?
+        .values
+        .first()?
+        .as_integer()
+        .and_then(|value| u8::try_from(value).ok())
+}
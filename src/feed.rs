@@ -0,0 +1,223 @@
+use anyhow::Context;
+use calcard::icalendar;
+use reqwest::header;
+
+use crate::{backend, eds, utils};
+
+// A calendar published as an ".ics" file on the web, like a shared on-call rotation, or,
+// the holidays of a country. It is fetched again only once the refresh interval is over,
+// and, the last copy of it is used while the feed cannot be reached.
+pub struct Feed {
+    url: reqwest::Url,
+    client: reqwest::Client,
+
+    // The name to show instead of the one the feed calls itself.
+    name: Option<String>,
+    refresh: std::time::Duration,
+}
+
+// The copy of a feed kept on the disk, with what is needed to ask the server whether it
+// has changed since.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Copy {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    // When the copy was last known to match the feed, in seconds since the epoch.
+    checked: i64,
+    ics: String,
+}
+
+impl Feed {
+    pub fn new(
+        url: &str,
+        name: Option<String>,
+        refresh: std::time::Duration,
+    ) -> anyhow::Result<Self> {
+        // The "webcal" scheme only tells that the URL is a calendar, the feeds are served
+        // over HTTPS.
+        let url = match url.split_once("://") {
+            Some(("webcal" | "webcals", rest)) => format!("https://{}", rest),
+            _ => url.to_owned(),
+        };
+
+        let url = reqwest::Url::parse(&url)
+            .with_context(|| format!("Could not parse \"{}\" as a URL", url))?;
+
+        Ok(Self {
+            url,
+            client: utils::http_client()?,
+            name,
+            refresh,
+        })
+    }
+
+    // Returns the feed, from the copy on the disk while it is fresh, or, from the server.
+    async fn load(&self) -> anyhow::Result<String> {
+        let path = utils::cache_path("feeds", self.url.as_str(), "json");
+        let copy: Option<Copy> = path
+            .as_ref()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok());
+
+        let now = chrono::Utc::now().timestamp();
+        if let Some(copy) = &copy
+            && now - copy.checked < self.refresh.as_secs() as i64
+        {
+            return Ok(copy.ics.clone());
+        }
+
+        let copy = match (self.fetch(copy.as_ref()).await, copy) {
+            (Ok(Some(fetched)), _) => fetched,
+            (Ok(None), Some(copy)) => Copy {
+                checked: now,
+                ..copy
+            },
+            (Ok(None), None) => anyhow::bail!("\"{}\" answered with nothing new", self.url),
+            // An offline feed is not worth failing over while there is a copy of it.
+            (Err(err), Some(copy)) => {
+                let checked = chrono::DateTime::from_timestamp(copy.checked, 0).map(|checked| {
+                    checked
+                        .with_timezone(&chrono::Local)
+                        .format("%b %-d %-l:%M%P")
+                });
+
+                match checked {
+                    Some(checked) => eprintln!("{:#}, using the copy from {}", err, checked),
+                    None => eprintln!("{:#}, using the last copy", err),
+                }
+
+                return Ok(copy.ics);
+            }
+            (Err(err), None) => return Err(err),
+        };
+
+        if let Some(path) = path
+            && let Err(err) = utils::write_file(&path, &serde_json::to_vec(&copy)?)
+        {
+            eprintln!("{:#}", err);
+        }
+
+        Ok(copy.ics)
+    }
+
+    // Fetches the feed, unless the server tells that it has not changed since the copy.
+    async fn fetch(&self, copy: Option<&Copy>) -> anyhow::Result<Option<Copy>> {
+        let mut request = self.client.get(self.url.clone());
+        if let Some(etag) = copy.and_then(|copy| copy.etag.as_ref()) {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = copy.and_then(|copy| copy.last_modified.as_ref()) {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Could not reach \"{}\"", self.url))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            anyhow::bail!("\"{}\" answered with {}", self.url, status);
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &header::HeaderValue| value.to_str().ok())
+                .map(|value| value.to_owned())
+        };
+        let etag = header(header::ETAG);
+        let last_modified = header(header::LAST_MODIFIED);
+
+        let ics = response
+            .text()
+            .await
+            .with_context(|| format!("Could not read \"{}\"", self.url))?;
+
+        if icalendar::ICalendar::parse(&ics).is_err() {
+            anyhow::bail!("\"{}\" is not a calendar", self.url);
+        }
+
+        Ok(Some(Copy {
+            etag,
+            last_modified,
+            checked: chrono::Utc::now().timestamp(),
+            ics,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl backend::Backend for Feed {
+    fn name(&self) -> String {
+        self.url.to_string()
+    }
+
+    async fn calendars(&self) -> anyhow::Result<Vec<Box<dyn backend::Calendar + '_>>> {
+        let ics = self.load().await?;
+
+        // The feeds usually carry their name, which is what they are picked by.
+        let name = self
+            .name
+            .clone()
+            .or_else(|| calendar_name(&ics))
+            .or_else(|| self.url.host_str().map(|host| host.to_owned()));
+
+        Ok(vec![Box::new(Calendar {
+            uid: self.url.to_string(),
+            name,
+            ics,
+        })])
+    }
+}
+
+pub struct Calendar {
+    uid: String,
+    name: Option<String>,
+
+    ics: String,
+}
+
+#[async_trait::async_trait]
+impl backend::Calendar for Calendar {
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    async fn fetch_events(
+        &self,
+        starts: chrono::DateTime<chrono::Local>,
+        ends: chrono::DateTime<chrono::Local>,
+        query: &eds::query::Query,
+    ) -> anyhow::Result<Vec<eds::event::Event>> {
+        let vevents =
+            eds::calendar::parse_components(&self.ics, &icalendar::ICalendarComponentType::VEvent);
+
+        Ok(backend::events_in_range(vevents, starts, ends, query))
+    }
+}
+
+// Returns the name the calendar calls itself, from the de facto "X-WR-CALNAME" property,
+// or, the standard "NAME" one.
+fn calendar_name(ics: &str) -> Option<String> {
+    let calendar = icalendar::ICalendar::parse(ics).ok()?;
+    let root = calendar.components.first()?;
+
+    ["X-WR-CALNAME", "NAME"].iter().find_map(|property| {
+        root.entries
+            .iter()
+            .find(|entry| entry.name.as_str().eq_ignore_ascii_case(property))
+            .and_then(|entry| entry.values.first())
+            .and_then(|value| value.as_text())
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty())
+    })
+}
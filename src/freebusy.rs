@@ -0,0 +1,334 @@
+use calcard::{common::PartialDateTime, icalendar};
+
+use crate::eds;
+
+// A span of time, from its start to its end, in UTC.
+pub type Interval = (chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>);
+
+// Returns the merged and sorted intervals the events keep you busy for. The transparent
+// and the cancelled events do not take up any time, and neither do the events without
+// an end.
+pub fn busy_intervals(events: &[eds::event::Event]) -> Vec<Interval> {
+    let mut intervals: Vec<Interval> = events
+        .iter()
+        .filter(|event| {
+            !event.transparent && event.status != Some(icalendar::ICalendarStatus::Cancelled)
+        })
+        .filter_map(|event| match (event.starts, event.ends) {
+            (Some(starts), Some(ends)) if starts < ends => Some((starts.to_utc(), ends.to_utc())),
+            _ => None,
+        })
+        .collect();
+
+    intervals.sort();
+
+    let mut merged: Vec<Interval> = Vec::new();
+    for (starts, ends) in intervals {
+        match merged.last_mut() {
+            Some((_, last_ends)) if starts <= *last_ends => *last_ends = ends.max(*last_ends),
+            _ => merged.push((starts, ends)),
+        }
+    }
+
+    merged
+}
+
+// Returns the working hours of every day in the range, clipped to the range. The weekends
+// are skipped unless they are asked for.
+pub fn working_windows<Tz: chrono::TimeZone>(
+    from: chrono::DateTime<Tz>,
+    to: chrono::DateTime<Tz>,
+    hours: (chrono::NaiveTime, chrono::NaiveTime),
+    weekends: bool,
+) -> Vec<Interval> {
+    let (day_starts, day_ends) = hours;
+    let tz = from.timezone();
+
+    from.date_naive()
+        .iter_days()
+        .take_while(|day| *day <= to.date_naive())
+        .filter(|day| {
+            weekends
+                || !matches!(
+                    chrono::Datelike::weekday(day),
+                    chrono::Weekday::Sat | chrono::Weekday::Sun
+                )
+        })
+        .filter_map(|day| {
+            let starts = day.and_time(day_starts).and_local_timezone(tz.clone());
+            let ends = day.and_time(day_ends).and_local_timezone(tz.clone());
+
+            let starts = starts.earliest()?.max(from.clone()).to_utc();
+            let ends = ends.latest()?.min(to.clone()).to_utc();
+
+            (starts < ends).then_some((starts, ends))
+        })
+        .collect()
+}
+
+// Returns the parts of the windows that are not busy and are at least as long as the
+// duration. The busy intervals are expected to be merged and sorted.
+pub fn free_slots(
+    windows: &[Interval],
+    busy: &[Interval],
+    duration: chrono::Duration,
+) -> Vec<Interval> {
+    let mut slots = Vec::new();
+
+    for (window_starts, window_ends) in windows.iter().copied() {
+        let mut cursor = window_starts;
+
+        for (busy_starts, busy_ends) in busy.iter().copied() {
+            if busy_ends <= cursor || busy_starts >= window_ends {
+                continue;
+            }
+
+            if busy_starts > cursor {
+                slots.push((cursor, busy_starts));
+            }
+
+            cursor = cursor.max(busy_ends);
+        }
+
+        if cursor < window_ends {
+            slots.push((cursor, window_ends));
+        }
+    }
+
+    slots.retain(|(starts, ends)| *ends - *starts >= duration);
+    slots
+}
+
+// Returns a VCALENDAR with a VFREEBUSY that reports the busy intervals in the range.
+pub fn to_vfreebusy(
+    busy: &[Interval],
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+) -> String {
+    let mut calendar = icalendar::ICalendarComponent {
+        component_type: icalendar::ICalendarComponentType::VCalendar,
+        component_ids: vec![1],
+        ..Default::default()
+    };
+
+    calendar.add_property(
+        icalendar::ICalendarProperty::Prodid,
+        icalendar::ICalendarValue::Text("-//calcal//EN".to_owned()),
+    );
+    calendar.add_property(
+        icalendar::ICalendarProperty::Version,
+        icalendar::ICalendarValue::Text("2.0".to_owned()),
+    );
+
+    let mut freebusy = icalendar::ICalendarComponent {
+        component_type: icalendar::ICalendarComponentType::VFreebusy,
+        ..Default::default()
+    };
+
+    freebusy.add_uid(&uuid::Uuid::new_v4().to_string());
+    freebusy.add_dtstamp(PartialDateTime::now());
+    freebusy.add_property(icalendar::ICalendarProperty::Dtstart, utc_value(from));
+    freebusy.add_property(icalendar::ICalendarProperty::Dtend, utc_value(to));
+
+    for (starts, ends) in busy.iter() {
+        if *ends <= from || *starts >= to {
+            continue;
+        }
+
+        freebusy.add_property_with_params(
+            icalendar::ICalendarProperty::Freebusy,
+            [icalendar::ICalendarParameter::Fbtype(
+                icalendar::ICalendarFreeBusyType::Busy,
+            )],
+            icalendar::ICalendarValue::Period(icalendar::ICalendarPeriod::Range {
+                start: utc_timestamp((*starts).max(from)),
+                end: utc_timestamp((*ends).min(to)),
+            }),
+        );
+    }
+
+    icalendar::ICalendar {
+        components: vec![calendar, freebusy],
+    }
+    .to_string()
+}
+
+fn utc_value(dt: chrono::DateTime<chrono::Utc>) -> icalendar::ICalendarValue {
+    icalendar::ICalendarValue::PartialDateTime(Box::new(utc_timestamp(dt)))
+}
+
+fn utc_timestamp(dt: chrono::DateTime<chrono::Utc>) -> PartialDateTime {
+    PartialDateTime::from_utc_timestamp(dt.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn utc(dt: &str) -> chrono::DateTime<chrono::Utc> {
+        dt.parse().unwrap()
+    }
+
+    fn event(properties: &str) -> eds::event::Event {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:event\r\n{}END:VEVENT\r\nEND:VCALENDAR\r\n",
+            properties.replace('\n', "\r\n"),
+        );
+        let trees =
+            eds::calendar::parse_components(&ics, &icalendar::ICalendarComponentType::VEvent);
+
+        eds::event::Event::from_tree(&trees[0])
+    }
+
+    fn hours(starts: &str, ends: &str) -> (chrono::NaiveTime, chrono::NaiveTime) {
+        let time = |time| chrono::NaiveTime::parse_from_str(time, "%H:%M").unwrap();
+        (time(starts), time(ends))
+    }
+
+    #[test]
+    fn merges_the_overlapping_and_the_adjacent_events() {
+        let events = [
+            event("DTSTART:20250106T130000Z\nDTEND:20250106T140000Z\n"),
+            event("DTSTART:20250106T090000Z\nDTEND:20250106T100000Z\n"),
+            event("DTSTART:20250106T093000Z\nDTEND:20250106T103000Z\n"),
+            event("DTSTART:20250106T103000Z\nDTEND:20250106T110000Z\n"),
+            // Inside the one before it, so, it does not cut the interval short.
+            event("DTSTART:20250106T131500Z\nDTEND:20250106T133000Z\n"),
+            event("DTSTART:20250106T120000Z\nDTEND:20250106T130000Z\n"),
+        ];
+
+        assert_eq!(
+            busy_intervals(&events),
+            [
+                (utc("2025-01-06T09:00:00Z"), utc("2025-01-06T11:00:00Z")),
+                (utc("2025-01-06T12:00:00Z"), utc("2025-01-06T14:00:00Z")),
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_out_the_events_that_do_not_take_up_time() {
+        let events = [
+            event("DTSTART:20250106T090000Z\nDTEND:20250106T100000Z\nTRANSP:TRANSPARENT\n"),
+            event("DTSTART:20250106T110000Z\nDTEND:20250106T120000Z\nSTATUS:CANCELLED\n"),
+            event("DTSTART:20250106T130000Z\n"),
+            event("DTSTART:20250106T150000Z\nDTEND:20250106T160000Z\nTRANSP:OPAQUE\n"),
+        ];
+
+        assert_eq!(
+            busy_intervals(&events),
+            [(utc("2025-01-06T15:00:00Z"), utc("2025-01-06T16:00:00Z"))]
+        );
+    }
+
+    #[test]
+    fn clips_the_working_hours_to_the_range() {
+        let tz = chrono_tz::Europe::Berlin;
+        // From a Friday afternoon to the next Tuesday morning.
+        let from = tz.with_ymd_and_hms(2025, 1, 10, 14, 0, 0).unwrap();
+        let to = tz.with_ymd_and_hms(2025, 1, 14, 11, 0, 0).unwrap();
+
+        assert_eq!(
+            working_windows(from, to, hours("09:00", "17:00"), false),
+            [
+                (utc("2025-01-10T13:00:00Z"), utc("2025-01-10T16:00:00Z")),
+                (utc("2025-01-13T08:00:00Z"), utc("2025-01-13T16:00:00Z")),
+                (utc("2025-01-14T08:00:00Z"), utc("2025-01-14T10:00:00Z")),
+            ]
+        );
+
+        assert_eq!(
+            working_windows(from, to, hours("09:00", "17:00"), true).len(),
+            5
+        );
+    }
+
+    #[test]
+    fn skips_the_days_the_range_ends_before_the_working_hours() {
+        let tz = chrono_tz::Europe::Berlin;
+        // From a Monday evening to the next morning, past midnight.
+        let from = tz.with_ymd_and_hms(2025, 1, 13, 18, 0, 0).unwrap();
+        let to = tz.with_ymd_and_hms(2025, 1, 14, 8, 0, 0).unwrap();
+
+        assert!(working_windows(from, to, hours("09:00", "17:00"), false).is_empty());
+
+        let to = tz.with_ymd_and_hms(2025, 1, 14, 10, 0, 0).unwrap();
+        assert_eq!(
+            working_windows(from, to, hours("09:00", "17:00"), false),
+            [(utc("2025-01-14T08:00:00Z"), utc("2025-01-14T09:00:00Z"))]
+        );
+    }
+
+    #[test]
+    fn keeps_the_working_hours_in_local_time_across_the_clock_changes() {
+        let tz = chrono_tz::Europe::Berlin;
+        // The clocks go forward on the night from Saturday to Sunday, March 30.
+        let from = tz.with_ymd_and_hms(2025, 3, 29, 0, 0, 0).unwrap();
+        let to = tz.with_ymd_and_hms(2025, 3, 31, 23, 0, 0).unwrap();
+
+        assert_eq!(
+            working_windows(from, to, hours("09:00", "17:00"), true),
+            [
+                (utc("2025-03-29T08:00:00Z"), utc("2025-03-29T16:00:00Z")),
+                (utc("2025-03-30T07:00:00Z"), utc("2025-03-30T15:00:00Z")),
+                (utc("2025-03-31T07:00:00Z"), utc("2025-03-31T15:00:00Z")),
+            ]
+        );
+
+        // The hour skipped that night is not worked.
+        let windows = working_windows(from, to, hours("01:00", "04:00"), true);
+        let lengths: Vec<_> = windows
+            .iter()
+            .map(|(starts, ends)| (*ends - *starts).num_hours())
+            .collect();
+        assert_eq!(lengths, [3, 2, 3]);
+    }
+
+    #[test]
+    fn finds_the_free_slots_that_are_long_enough() {
+        let windows = [
+            (utc("2025-01-06T09:00:00Z"), utc("2025-01-06T17:00:00Z")),
+            (utc("2025-01-07T09:00:00Z"), utc("2025-01-07T17:00:00Z")),
+        ];
+        let busy = [
+            // Overlaps with the start of the first window.
+            (utc("2025-01-06T08:00:00Z"), utc("2025-01-06T10:00:00Z")),
+            // Leaves a gap of 30 minutes only.
+            (utc("2025-01-06T10:30:00Z"), utc("2025-01-06T12:00:00Z")),
+            // Leaves a gap of an hour exactly.
+            (utc("2025-01-06T13:00:00Z"), utc("2025-01-06T16:30:00Z")),
+            // Runs over the night into the second window.
+            (utc("2025-01-06T16:45:00Z"), utc("2025-01-07T11:00:00Z")),
+        ];
+
+        assert_eq!(
+            free_slots(&windows, &busy, chrono::Duration::hours(1)),
+            [
+                (utc("2025-01-06T12:00:00Z"), utc("2025-01-06T13:00:00Z")),
+                (utc("2025-01-07T11:00:00Z"), utc("2025-01-07T17:00:00Z")),
+            ]
+        );
+
+        assert_eq!(
+            free_slots(&windows, &busy, chrono::Duration::minutes(15)),
+            [
+                (utc("2025-01-06T10:00:00Z"), utc("2025-01-06T10:30:00Z")),
+                (utc("2025-01-06T12:00:00Z"), utc("2025-01-06T13:00:00Z")),
+                (utc("2025-01-06T16:30:00Z"), utc("2025-01-06T16:45:00Z")),
+                (utc("2025-01-07T11:00:00Z"), utc("2025-01-07T17:00:00Z")),
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_the_windows_free_without_events() {
+        let windows = [(utc("2025-01-06T09:00:00Z"), utc("2025-01-06T17:00:00Z"))];
+        assert_eq!(
+            free_slots(&windows, &[], chrono::Duration::hours(8)),
+            windows
+        );
+        assert!(free_slots(&windows, &[], chrono::Duration::hours(9)).is_empty());
+    }
+}
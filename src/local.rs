@@ -0,0 +1,313 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use calcard::icalendar;
+
+use crate::{backend, eds};
+
+// The calendars stored as iCalendar files on the disk. The path is either a single ".ics"
+// file, a vdir, that is, a directory with one ".ics" file per event, like the ones khal
+// and vdirsyncer use, or, a directory with vdirs in it.
+pub struct Local {
+    path: PathBuf,
+}
+
+impl Local {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait::async_trait]
+impl backend::Backend for Local {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    async fn calendars(&self) -> anyhow::Result<Vec<Box<dyn backend::Calendar + '_>>> {
+        Ok(discover(&self.path)?
+            .into_iter()
+            .map(|calendar| Box::new(calendar) as Box<dyn backend::Calendar>)
+            .collect())
+    }
+
+    // The vdirs can hold tasks as well, so, every calendar is a task list too.
+    async fn task_lists(&self) -> anyhow::Result<Vec<Box<dyn backend::Calendar + '_>>> {
+        self.calendars().await
+    }
+}
+
+pub struct Calendar {
+    path: PathBuf,
+
+    uid: String,
+    name: String,
+
+    // The name of the directory the vdir was found in, if a directory of vdirs was given.
+    account: Option<String>,
+}
+
+impl Calendar {
+    fn new(path: &Path, account: Option<String>) -> Self {
+        // The vdirs keep their name in a file of their own.
+        let name = std::fs::read_to_string(path.join("displayname"))
+            .ok()
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| file_name(path));
+
+        Self {
+            path: path.to_owned(),
+            uid: path.display().to_string(),
+            name,
+            account,
+        }
+    }
+
+    // Returns the components of the type from all the files of the calendar.
+    fn components(
+        &self,
+        component_type: &icalendar::ICalendarComponentType,
+    ) -> anyhow::Result<Vec<icalendar::ICalendar>> {
+        let files = match self.path.is_file() {
+            true => vec![self.path.clone()],
+            false => ics_files(&self.path)?,
+        };
+
+        let mut components = Vec::new();
+        for file in files {
+            let ics = std::fs::read_to_string(&file)
+                .with_context(|| format!("Could not read \"{}\"", file.display()))?;
+            components.extend(eds::calendar::parse_components(&ics, component_type));
+        }
+
+        Ok(components)
+    }
+}
+
+#[async_trait::async_trait]
+impl backend::Calendar for Calendar {
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn path(&self) -> Option<String> {
+        self.account
+            .as_ref()
+            .map(|account| format!("{}/{}", account, self.name))
+    }
+
+    // The files do not tell us when the events happen, so, they are all parsed.
+    async fn fetch_events(
+        &self,
+        starts: chrono::DateTime<chrono::Local>,
+        ends: chrono::DateTime<chrono::Local>,
+        query: &eds::query::Query,
+    ) -> anyhow::Result<Vec<eds::event::Event>> {
+        let vevents = self.components(&icalendar::ICalendarComponentType::VEvent)?;
+        Ok(backend::events_in_range(vevents, starts, ends, query))
+    }
+
+    async fn fetch_tasks(&self) -> anyhow::Result<Vec<eds::task::Task>> {
+        let vtodos = self.components(&icalendar::ICalendarComponentType::VTodo)?;
+        Ok(eds::calendar::tasks_from_vtodos(vtodos.iter()))
+    }
+}
+
+// Returns the calendars found at the path.
+fn discover(path: &Path) -> anyhow::Result<Vec<Calendar>> {
+    let metadata = std::fs::metadata(path)
+        .with_context(|| format!("Could not read \"{}\"", path.display()))?;
+
+    if metadata.is_file() || is_vdir(path)? {
+        return Ok(vec![Calendar::new(path, None)]);
+    }
+
+    // Otherwise, it is a directory of vdirs, like the ones vdirsyncer syncs into.
+    let account = file_name(path);
+
+    let mut dirs: Vec<_> = std::fs::read_dir(path)
+        .with_context(|| format!("Could not list \"{}\"", path.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    dirs.sort();
+
+    let mut calendars = Vec::new();
+    for dir in dirs {
+        if is_vdir(&dir)? {
+            calendars.push(Calendar::new(&dir, Some(account.clone())));
+        }
+    }
+
+    Ok(calendars)
+}
+
+// Returns whether the directory holds the events of a single calendar.
+fn is_vdir(dir: &Path) -> anyhow::Result<bool> {
+    Ok(dir.join("displayname").is_file() || !ics_files(dir)?.is_empty())
+}
+
+// Returns the ".ics" files in the directory, in a stable order.
+fn ics_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("Could not list \"{}\"", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.is_file() && file.extension().is_some_and(|ext| ext == "ics"))
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+// Returns the name of the file, or the directory, without its extension.
+fn file_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use backend::Calendar as _;
+
+    use super::*;
+
+    static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+    // A directory of its own for the test, removed once it is done.
+    struct Dir(PathBuf);
+
+    impl Dir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "calcal-local-{}-{}",
+                std::process::id(),
+                DIRS.fetch_add(1, Ordering::SeqCst),
+            ));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, path: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn ics(components: &[String]) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}END:VCALENDAR\r\n",
+            components.concat()
+        )
+    }
+
+    fn vevent(uid: &str, title: &str, day: u32) -> String {
+        format!(
+            "BEGIN:VEVENT\r\nUID:{}\r\nDTSTART:202501{:02}T100000Z\r\nDURATION:PT1H\r\n\
+             SUMMARY:{}\r\nEND:VEVENT\r\n",
+            uid, day, title
+        )
+    }
+
+    fn vtodo(uid: &str, title: &str) -> String {
+        format!(
+            "BEGIN:VTODO\r\nUID:{}\r\nSUMMARY:{}\r\nEND:VTODO\r\n",
+            uid, title
+        )
+    }
+
+    // Returns the titles of the events on the calendar in the first half of January.
+    async fn titles(calendar: &Calendar) -> Vec<String> {
+        let starts =
+            chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2025, 1, 1, 0, 0, 0).unwrap();
+        let ends =
+            chrono::TimeZone::with_ymd_and_hms(&chrono::Local, 2025, 1, 15, 0, 0, 0).unwrap();
+
+        let mut titles: Vec<_> = calendar
+            .fetch_events(starts, ends, &eds::query::Query::All)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|event| event.title)
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    #[tokio::test]
+    async fn reads_the_events_of_a_single_file() {
+        let dir = Dir::new();
+        let file = dir.write(
+            "holidays.ics",
+            &ics(&[
+                vevent("new-year", "New Year", 1),
+                vevent("epiphany", "Epiphany", 6),
+                vevent("carnival", "Carnival", 20),
+            ]),
+        );
+
+        let calendars = discover(&file).unwrap();
+        assert_eq!(calendars.len(), 1);
+        assert_eq!(calendars[0].name(), Some("holidays"));
+        assert_eq!(calendars[0].path(), None);
+        assert_eq!(titles(&calendars[0]).await, ["Epiphany", "New Year"]);
+    }
+
+    #[tokio::test]
+    async fn reads_a_vdir_one_event_per_file() {
+        let dir = Dir::new();
+        dir.write("work/displayname", "Work\n");
+        dir.write("work/standup.ics", &ics(&[vevent("standup", "Standup", 6)]));
+        dir.write("work/review.ics", &ics(&[vevent("review", "Review", 7)]));
+        dir.write("work/notes.txt", &ics(&[vevent("notes", "Notes", 8)]));
+        dir.write("work/todo.ics", &ics(&[vtodo("report", "Write Report")]));
+
+        let calendars = discover(&dir.0.join("work")).unwrap();
+        assert_eq!(calendars.len(), 1);
+        assert_eq!(calendars[0].name(), Some("Work"));
+        assert_eq!(titles(&calendars[0]).await, ["Review", "Standup"]);
+
+        let tasks = calendars[0].fetch_tasks().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title.as_deref(), Some("Write Report"));
+    }
+
+    #[tokio::test]
+    async fn finds_the_vdirs_in_a_directory() {
+        let dir = Dir::new();
+        dir.write("account/work/displayname", "Work\n");
+        dir.write(
+            "account/personal/dentist.ics",
+            &ics(&[vevent("dentist", "Dentist", 9)]),
+        );
+        dir.write("account/empty/readme.txt", "Nothing here\n");
+
+        let calendars = discover(&dir.0.join("account")).unwrap();
+        let paths: Vec<_> = calendars.iter().filter_map(|c| c.path()).collect();
+        assert_eq!(paths, ["account/personal", "account/Work"]);
+
+        assert_eq!(titles(&calendars[0]).await, ["Dentist"]);
+        assert!(titles(&calendars[1]).await.is_empty());
+    }
+
+    #[test]
+    fn fails_on_a_missing_path() {
+        let dir = Dir::new();
+        assert!(discover(&dir.0.join("missing")).is_err());
+    }
+}
@@ -2,139 +2,1180 @@ use anyhow::Context;
 use calcard::icalendar;
 use clap::Parser;
 
+use backend::{Calendar as _, Failure};
+
+mod backend;
+mod caldav;
+mod config;
 mod eds;
+mod feed;
+mod freebusy;
+mod local;
+mod notify;
+mod selector;
 mod utils;
+mod watch;
 
 #[derive(Debug, clap::Parser)]
 #[command(about = "Retrieves upcoming events from your calendar.")]
 struct Cli {
     #[command(subcommand)]
     command: Command,
+
+    /// If enabled, the calendars that are disabled, or were hidden on the calendar apps,
+    /// are used too.
+    #[arg(long, global = true)]
+    include_hidden: bool,
+
+    /// The calendars to skip, picked the same way as the calendars to fetch from.
+    #[arg(short = 'x', long, global = true)]
+    exclude: Vec<selector::Selector>,
+
+    /// The statuses of the events and the tasks to use. Defaults to all of them but
+    /// cancelled.
+    #[arg(long, global = true, value_enum, value_delimiter = ',')]
+    status: Vec<eds::status::Status>,
+
+    /// The statuses of the events and the tasks to skip. The cancelled ones are skipped
+    /// unless they are asked for with "--status".
+    #[arg(long, global = true, value_enum, value_delimiter = ',')]
+    exclude_status: Vec<eds::status::Status>,
+
+    /// If enabled, the events you tentatively accepted are left out of the summary, the
+    /// events of the day and Waybar, like the ones you declined.
+    #[arg(long, global = true)]
+    hide_tentative: bool,
+
+    /// If enabled, the invitations you did not reply to yet are left out of the summary,
+    /// the events of the day and Waybar, like the ones you declined.
+    #[arg(long, global = true)]
+    hide_unanswered: bool,
+
+    /// An ".ics" file, a vdir, a directory of vdirs, or the URL of a CalDAV server, to read
+    /// the calendars from instead of the EDS, or, the URL of an ".ics" feed to read along
+    /// with them. Defaults to the sources in the config file.
+    #[arg(long, global = true)]
+    source: Vec<config::Source>,
+}
+
+// The calendars picked on the command line.
+#[derive(Debug, Clone, Default)]
+struct Selection {
+    whitelist: Option<Vec<selector::Selector>>,
+    exclude: Vec<selector::Selector>,
+    include_hidden: bool,
+
+    // The addresses of the user from the config, and, the replies to the invitations
+    // that leave the events out of the near events.
+    addresses: Vec<String>,
+    hidden_replies: Vec<icalendar::ICalendarParticipationStatus>,
+
+    // The statuses of the events and the tasks to use, if only some of them, and, the
+    // ones to skip.
+    statuses: Option<Vec<eds::status::Status>>,
+    excluded_statuses: Vec<eds::status::Status>,
+}
+
+impl Selection {
+    // Returns the same selection but with all the calendars whitelisted.
+    fn without_whitelist(&self) -> Self {
+        Self {
+            whitelist: None,
+            ..self.clone()
+        }
+    }
+
+    // Returns the same selection but with the components of every status.
+    fn with_all_statuses(&self) -> Self {
+        Self {
+            statuses: None,
+            excluded_statuses: vec![],
+            ..self.clone()
+        }
+    }
+
+    // Returns whether the events, or the tasks, with the status are used.
+    fn picks_status(&self, status: Option<eds::status::Status>) -> bool {
+        match status {
+            Some(status) => {
+                self.statuses
+                    .as_ref()
+                    .is_none_or(|statuses| statuses.contains(&status))
+                    && !self.excluded_statuses.contains(&status)
+            }
+            None => self.statuses.is_none(),
+        }
+    }
+
+    // Returns whether the event is used, by its status.
+    fn picks_event(&self, event: &eds::event::Event) -> bool {
+        self.picks_status(eds::status::Status::of(
+            event.status.as_ref(),
+            eds::status::Status::Confirmed,
+        ))
+    }
+
+    // Returns whether the task is used, by its status.
+    fn picks_task(&self, task: &eds::task::Task) -> bool {
+        self.picks_status(eds::status::Status::of(
+            task.status.as_ref(),
+            eds::status::Status::NeedsAction,
+        ))
+    }
 }
 
 #[derive(Debug, clap::Subcommand)]
 enum Command {
     /// List all the calendars available to calcal.
-    Calendars,
+    Calendars {
+        /// If enabled, the calendars are printed as JSON instead of a table.
+        #[arg(short, long)]
+        json: bool,
+    },
 
     /// Generates a summary of all the ongoing events and upcoming events.
     Summary {
-        /// The whitelist of calendars to fetch the events from. Defaults to all calendars.
+        /// The calendars to fetch the events from, by UID, name, "account/name", glob or "re:"
+        /// regex. Defaults to all calendars.
         #[arg(short, long)]
-        calendars: Option<Vec<String>>,
+        calendars: Option<Vec<selector::Selector>>,
 
         /// If enabled, the summary will only contain events from today.
         #[arg(short, long, default_value_t = true)]
         limit_to_today: bool,
+
+        /// If enabled, the all-day events count as ongoing events in the summary too.
+        #[arg(long)]
+        with_all_day: bool,
+
+        /// If enabled, the number of tasks due today is included too.
+        #[arg(long)]
+        with_tasks: bool,
+    },
+
+    /// Keeps printing the summary every time it changes.
+    Watch {
+        /// The calendars to fetch the events from, by UID, name, "account/name", glob or "re:"
+        /// regex. Defaults to all calendars.
+        #[arg(short, long)]
+        calendars: Option<Vec<selector::Selector>>,
+
+        /// If enabled, the summary will only contain events from today.
+        #[arg(short, long, default_value_t = true)]
+        limit_to_today: bool,
+
+        /// If enabled, the all-day events count as ongoing events in the summary too.
+        #[arg(long)]
+        with_all_day: bool,
+
+        /// If enabled, the number of tasks due today is included too.
+        #[arg(long)]
+        with_tasks: bool,
     },
 
     /// Generates a simple table of all the events today.
     Today {
-        /// The whitelist of calendars to fetch the events from. Defaults to all calendars.
+        /// The calendars to fetch the events from, by UID, name, "account/name", glob or "re:"
+        /// regex. Defaults to all calendars.
+        #[arg(short, long)]
+        calendars: Option<Vec<selector::Selector>>,
+
+        /// If enabled, the events are printed as JSON instead of a table, with all of their
+        /// details, like the location, the attendees and the calendar they are on.
+        #[arg(short, long, conflicts_with = "with_tasks")]
+        json: bool,
+
+        /// If enabled, the number of tasks due today is included too.
+        #[arg(long)]
+        with_tasks: bool,
+    },
+
+    /// Lists the open tasks from the task lists.
+    Tasks {
+        /// The task lists to fetch the tasks from, picked like the calendars. Defaults to all
+        /// task lists.
+        #[arg(short, long)]
+        calendars: Option<Vec<selector::Selector>>,
+
+        /// If enabled, the completed and cancelled tasks are listed too, whatever the
+        /// statuses picked.
+        #[arg(short, long)]
+        all: bool,
+    },
+
+    /// Lists the memos from the memo lists.
+    Memos {
+        /// The memo lists to fetch the memos from, picked like the calendars. Defaults to all
+        /// memo lists.
+        #[arg(short, long)]
+        calendars: Option<Vec<selector::Selector>>,
+
+        /// Only list the memos that contain this text, ignoring the case.
+        #[arg(short, long)]
+        search: Option<String>,
+    },
+
+    /// Lists the events that contain the text, ignoring the case.
+    Search {
+        /// The text to look for.
+        text: String,
+
+        /// The calendars to fetch the events from, by UID, name, "account/name", glob or "re:"
+        /// regex. Defaults to all calendars.
+        #[arg(short, long)]
+        calendars: Option<Vec<selector::Selector>>,
+
+        /// Where to start looking for the events from. Defaults to now.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        from: Option<chrono::DateTime<chrono::Local>>,
+
+        /// Where to stop looking for the events. Defaults to 90 days after the start.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        to: Option<chrono::DateTime<chrono::Local>>,
+
+        /// The fields to look for the text in. Defaults to any of them.
+        #[arg(long, value_enum)]
+        field: Vec<eds::query::Field>,
+    },
+
+    /// Lists the upcoming alarms of the events, as they were configured on the calendars.
+    Alarms {
+        /// The calendars to fetch the events from, by UID, name, "account/name", glob or "re:"
+        /// regex. Defaults to all calendars.
+        #[arg(short, long)]
+        calendars: Option<Vec<selector::Selector>>,
+
+        /// How far ahead to look for alarms, e.g. "1h" or "1day".
+        #[arg(short, long, value_parser = humantime::parse_duration, default_value = "1h")]
+        within: std::time::Duration,
+    },
+
+    /// Keeps running and shows a desktop notification when an alarm of an event goes off.
+    Notify {
+        /// The calendars to fetch the events from, by UID, name, "account/name", glob or "re:"
+        /// regex. Defaults to all calendars.
+        #[arg(short, long)]
+        calendars: Option<Vec<selector::Selector>>,
+
+        /// If given, the events without any alarms are reminded of this long before they start.
+        #[arg(short, long, value_parser = humantime::parse_duration)]
+        lead: Option<std::time::Duration>,
+
+        /// How long the "Snooze" action on a notification puts off the reminder.
+        #[arg(short, long, value_parser = humantime::parse_duration, default_value = "5m")]
+        snooze: std::time::Duration,
+    },
+
+    /// Lists the free slots in the working hours that are long enough for a meeting.
+    Free {
+        /// The calendars to fetch the events from, by UID, name, "account/name", glob or "re:"
+        /// regex. Defaults to all calendars.
+        #[arg(short, long)]
+        calendars: Option<Vec<selector::Selector>>,
+
+        /// The least duration of a free slot, e.g. "30m" or "1h".
+        #[arg(short, long, value_parser = humantime::parse_duration, default_value = "30m")]
+        duration: std::time::Duration,
+
+        /// Where to start looking for free slots from. Defaults to now.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        from: Option<chrono::DateTime<chrono::Local>>,
+
+        /// Where to stop looking for free slots. Defaults to a week after the start.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        to: Option<chrono::DateTime<chrono::Local>>,
+
+        /// The working hours of a day, the free slots are only looked for in them.
+        #[arg(long, value_parser = utils::parse_time_range, default_value = "09:00-17:00")]
+        hours: (chrono::NaiveTime, chrono::NaiveTime),
+
+        /// If enabled, the free slots on the weekends are listed too.
+        #[arg(long)]
+        weekends: bool,
+
+        /// If enabled, the busy time in the range is printed as a VFREEBUSY instead.
+        #[arg(long)]
+        vfreebusy: bool,
+    },
+
+    /// Creates a new event on a calendar and prints its UID.
+    #[command(group(clap::ArgGroup::new("length").required(true).args(["ends", "duration"])))]
+    Add {
+        /// The title of the event.
+        title: String,
+
+        /// When the event starts, e.g. "2025-08-20 14:00", or "14:00" for today.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        starts: chrono::DateTime<chrono::Local>,
+
+        /// When the event ends, in the same formats as the start.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        ends: Option<chrono::DateTime<chrono::Local>>,
+
+        /// How long the event lasts, e.g. "30m" or "1h 30m".
+        #[arg(short, long, value_parser = humantime::parse_duration)]
+        duration: Option<std::time::Duration>,
+
+        /// Where the event takes place.
+        #[arg(long)]
+        location: Option<String>,
+
+        /// A longer description of the event.
+        #[arg(long)]
+        description: Option<String>,
+
+        /// The name or the UID of the calendar to create the event on.
         #[arg(short, long)]
-        calendars: Option<Vec<String>>,
+        calendar: String,
+    },
+
+    /// Changes the title, the time or the location of an event.
+    Edit {
+        /// The UID of the event.
+        uid: String,
+
+        /// The start of the occurrence to change, if the event is recurring.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        at: Option<chrono::DateTime<chrono::Local>>,
+
+        /// The occurrences of a recurring event to change.
+        /// Defaults to "this" with an occurrence and "all" otherwise.
+        #[arg(long, value_enum)]
+        scope: Option<eds::calendar::ModType>,
+
+        /// The name or the UID of the calendar with the event. Defaults to looking it up.
+        #[arg(short, long)]
+        calendar: Option<String>,
+
+        /// The new title of the event.
+        #[arg(short, long)]
+        title: Option<String>,
+
+        /// The new start of the event. The event keeps its duration unless an end is given.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        starts: Option<chrono::DateTime<chrono::Local>>,
+
+        /// The new end of the event.
+        #[arg(short, long, value_parser = utils::parse_local_datetime, conflicts_with = "duration")]
+        ends: Option<chrono::DateTime<chrono::Local>>,
+
+        /// The new duration of the event, e.g. "30m" or "1h 30m".
+        #[arg(short, long, value_parser = humantime::parse_duration)]
+        duration: Option<std::time::Duration>,
+
+        /// The new location of the event.
+        #[arg(long)]
+        location: Option<String>,
+    },
+
+    /// Deletes an event, or some of the occurrences of a recurring event.
+    Rm {
+        /// The UID of the event.
+        uid: String,
+
+        /// The start of the occurrence to delete, if the event is recurring.
+        #[arg(short, long, value_parser = utils::parse_local_datetime)]
+        at: Option<chrono::DateTime<chrono::Local>>,
+
+        /// The occurrences of a recurring event to delete.
+        /// Defaults to "this" with an occurrence and "all" otherwise.
+        #[arg(long, value_enum)]
+        scope: Option<eds::calendar::ModType>,
+
+        /// The name or the UID of the calendar with the event. Defaults to looking it up.
+        #[arg(short, long)]
+        calendar: Option<String>,
     },
 
     /// Emits calendar information in a Waybar compatible JSON schema.
     Waybar {
-        /// The whitelist of calendars to fetch the events from. Defaults to all calendars.
+        /// The calendars to fetch the events from, by UID, name, "account/name", glob or "re:"
+        /// regex. Defaults to all calendars.
         #[arg(short, long)]
-        calendars: Option<Vec<String>>,
+        calendars: Option<Vec<selector::Selector>>,
 
         /// If enabled, the summary will only contain events from today.
         #[arg(short, long, default_value_t = true)]
         limit_to_today: bool,
+
+        /// If enabled, the all-day events count as ongoing events in the summary too.
+        #[arg(long)]
+        with_all_day: bool,
+
+        /// If enabled, keeps running and emits a new line every time the output changes.
+        #[arg(short, long)]
+        watch: bool,
+
+        /// If enabled, the number of tasks due today is included too.
+        #[arg(long)]
+        with_tasks: bool,
     },
 }
 
+impl Command {
+    // Whether the command reads the sources or the addresses from the config. The rest
+    // only work with the EDS, so, they keep working with a config that cannot be read.
+    fn uses_config(&self) -> bool {
+        matches!(
+            self,
+            Command::Summary { .. }
+                | Command::Watch { .. }
+                | Command::Today { .. }
+                | Command::Tasks { .. }
+                | Command::Search { .. }
+                | Command::Alarms { .. }
+                | Command::Free { .. }
+                | Command::Waybar { .. }
+        )
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    let conn = zbus::connection::Builder::session()
-        .context("Could not build dbus session")?
-        .build()
-        .await
-        .context("Could not connect to session dbus")?;
+    let config::Config { sources, addresses } = match cli.command.uses_config() {
+        true => config::Config::load()?,
+        false => config::Config::default(),
+    };
+
+    // The events you declined are never worth showing, the rest depends on how you use
+    // the invitations.
+    let mut hidden_replies = vec![icalendar::ICalendarParticipationStatus::Declined];
+    if cli.hide_tentative {
+        hidden_replies.push(icalendar::ICalendarParticipationStatus::Tentative);
+    }
+    if cli.hide_unanswered {
+        hidden_replies.push(icalendar::ICalendarParticipationStatus::NeedsAction);
+    }
+
+    // The cancelled events are gone from the calendars for all that matters, unless they
+    // are asked for.
+    let mut excluded_statuses = cli.exclude_status;
+    if !cli.status.contains(&eds::status::Status::Cancelled) {
+        excluded_statuses.push(eds::status::Status::Cancelled);
+    }
+    let statuses = Some(cli.status).filter(|statuses| !statuses.is_empty());
+
+    let (exclude, include_hidden) = (cli.exclude, cli.include_hidden);
+    let selection = |whitelist| Selection {
+        whitelist,
+        exclude: exclude.clone(),
+        include_hidden,
+        addresses: addresses.clone(),
+        hidden_replies: hidden_replies.clone(),
+        statuses: statuses.clone(),
+        excluded_statuses: excluded_statuses.clone(),
+    };
+
+    let sources = match cli.source.is_empty() {
+        true => sources,
+        false => cli.source,
+    };
+
+    let conn = connect().await;
+
+    // Only the commands that work with the EDS alone need the connection to succeed.
+    let session = || conn.as_ref().map_err(|err| anyhow::anyhow!("{:#}", err));
+
+    // The feeds are shown next to the other calendars, while the other sources take the
+    // place of the EDS, for the commands that can read from them.
+    let (feeds, sources): (Vec<_>, Vec<_>) =
+        sources.into_iter().partition(|source| source.is_feed());
+
+    // The backends are only opened by the commands that read from them, as opening some of
+    // them runs a command for the password.
+    let backend = || open_backend(&conn, &sources, &feeds);
+
+    // The live views are only available on the EDS.
+    let watchable = || match sources.is_empty() {
+        true => session(),
+        false => {
+            anyhow::bail!("Only the EDS calendars can be watched, run it on an interval instead")
+        }
+    };
 
     match cli.command {
-        Command::Calendars => {
-            calendars(&conn).await.context("Could not list calendars")?;
+        Command::Calendars { json } => {
+            calendars(session()?, &selection(None), json)
+                .await
+                .context("Could not list calendars")?;
         }
 
         Command::Summary {
             calendars,
             limit_to_today,
+            with_all_day,
+            with_tasks,
         } => {
+            let backend = backend()?;
             println!(
                 "{}",
-                summary(&conn, calendars, limit_to_today)
-                    .await
-                    .context("Could not generate summary")?,
+                summary(
+                    backend.as_ref(),
+                    &selection(calendars),
+                    limit_to_today,
+                    with_all_day,
+                    with_tasks,
+                )
+                .await
+                .context("Could not generate summary")?,
             );
         }
 
-        Command::Today { calendars } => {
+        Command::Watch {
+            calendars,
+            limit_to_today,
+            with_all_day,
+            with_tasks,
+        } => {
+            watch::watch(
+                watchable()?,
+                &selection(calendars),
+                with_tasks,
+                |events, tasks, _| summarize(events, tasks, limit_to_today, with_all_day),
+            )
+            .await
+            .context("Could not watch calendars")?;
+        }
+
+        Command::Today {
+            calendars,
+            json,
+            with_tasks,
+        } => {
+            let backend = backend()?;
             println!(
                 "{}",
-                today(&conn, calendars)
+                today(backend.as_ref(), &selection(calendars), json, with_tasks)
                     .await
                     .context("Could not generate full calendar")?,
             )
         }
 
+        Command::Tasks { calendars, all } => {
+            let backend = backend()?;
+            println!(
+                "{}",
+                tasks(backend.as_ref(), &selection(calendars), all)
+                    .await
+                    .context("Could not list tasks")?,
+            )
+        }
+
+        Command::Memos { calendars, search } => {
+            println!(
+                "{}",
+                memos(session()?, &selection(calendars), search)
+                    .await
+                    .context("Could not list memos")?,
+            )
+        }
+
+        Command::Search {
+            text,
+            calendars,
+            from,
+            to,
+            field,
+        } => {
+            let from = from.unwrap_or_else(chrono::Local::now);
+            let to = to.unwrap_or(from + chrono::Duration::days(90));
+            if to <= from {
+                anyhow::bail!("The end of the range should be after its start");
+            }
+
+            let backend = backend()?;
+            println!(
+                "{}",
+                search(
+                    backend.as_ref(),
+                    &selection(calendars),
+                    &text,
+                    &field,
+                    from,
+                    to
+                )
+                .await
+                .context("Could not search events")?,
+            )
+        }
+
+        Command::Alarms { calendars, within } => {
+            let backend = backend()?;
+            println!(
+                "{}",
+                alarms(
+                    backend.as_ref(),
+                    &selection(calendars),
+                    chrono::Duration::from_std(within)
+                        .context("Could not determine the range of the alarms")?,
+                )
+                .await
+                .context("Could not list alarms")?,
+            )
+        }
+
+        Command::Notify {
+            calendars,
+            lead,
+            snooze,
+        } => {
+            let lead = match lead {
+                Some(lead) => Some(
+                    chrono::Duration::from_std(lead)
+                        .context("Could not determine the lead time")?,
+                ),
+                None => None,
+            };
+
+            notify::notify(
+                session()?,
+                &selection(calendars),
+                lead,
+                chrono::Duration::from_std(snooze)
+                    .context("Could not determine the snooze time")?,
+            )
+            .await
+            .context("Could not send notifications")?;
+        }
+
+        Command::Free {
+            calendars,
+            duration,
+            from,
+            to,
+            hours,
+            weekends,
+            vfreebusy,
+        } => {
+            let from = from.unwrap_or_else(chrono::Local::now);
+            let to = to.unwrap_or(from + chrono::Duration::weeks(1));
+            if to <= from {
+                anyhow::bail!("The end of the range should be after its start");
+            }
+
+            let backend = backend()?;
+            let (events, failures) = events_between(
+                backend.as_ref(),
+                &selection(calendars),
+                from,
+                to,
+                &eds::query::Query::All,
+            )
+            .await
+            .context("Could not fetch events")?;
+            report_failures(&failures);
+
+            let busy = freebusy::busy_intervals(&events);
+
+            if vfreebusy {
+                print!(
+                    "{}",
+                    freebusy::to_vfreebusy(&busy, from.to_utc(), to.to_utc())
+                );
+            } else {
+                println!(
+                    "{}",
+                    free(
+                        &busy,
+                        freebusy::working_windows(from, to, hours, weekends),
+                        chrono::Duration::from_std(duration)
+                            .context("Could not determine the duration of the slots")?,
+                    )
+                );
+            }
+        }
+
+        Command::Add {
+            title,
+            starts,
+            ends,
+            duration,
+            location,
+            description,
+            calendar,
+        } => {
+            let ends = match (ends, duration) {
+                (Some(ends), _) => ends,
+                (None, Some(duration)) => {
+                    starts
+                        + chrono::Duration::from_std(duration)
+                            .context("Could not determine the end of the event")?
+                }
+                (None, None) => anyhow::bail!("Either the end or the duration is required"),
+            };
+
+            if ends <= starts {
+                anyhow::bail!("The event should end after it starts");
+            }
+
+            let event = eds::event::NewEvent {
+                title,
+                description,
+                location,
+                starts,
+                ends,
+            };
+
+            let uid = find_calendar(session()?, &calendar)
+                .await?
+                .create_event(&event)
+                .await
+                .context("Could not add event")?;
+
+            println!("{}", uid);
+        }
+
+        Command::Edit {
+            uid,
+            at,
+            scope,
+            calendar,
+            title,
+            starts,
+            ends,
+            duration,
+            location,
+        } => {
+            let changes = eds::event::EventChanges {
+                title,
+                location,
+                starts,
+                ends,
+                duration: duration
+                    .map(chrono::Duration::from_std)
+                    .transpose()
+                    .context("Could not determine the duration of the event")?,
+            };
+
+            edit(session()?, &uid, calendar.as_deref(), at, scope, changes)
+                .await
+                .context("Could not edit event")?;
+        }
+
+        Command::Rm {
+            uid,
+            at,
+            scope,
+            calendar,
+        } => {
+            remove(session()?, &uid, calendar.as_deref(), at, scope)
+                .await
+                .context("Could not delete event")?;
+        }
+
         Command::Waybar {
             calendars,
             limit_to_today,
+            with_all_day,
+            watch,
+            with_tasks,
         } => {
-            // https://man.archlinux.org/man/extra/waybar/waybar-custom.5.en#RETURN-TYPE
-            let value = serde_json::json!({
-                "text": summary(&conn, calendars.clone(), limit_to_today)
+            if watch {
+                watch::watch(
+                    watchable()?,
+                    &selection(calendars),
+                    with_tasks,
+                    |events, tasks, failures| {
+                        waybar(events, tasks, failures, limit_to_today, with_all_day).to_string()
+                    },
+                )
+                .await
+                .context("Could not watch calendars")?;
+            } else {
+                let selection = selection(calendars);
+                let backend = backend()?;
+                let (near_events, mut failures) = near_events(backend.as_ref(), &selection)
                     .await
-                    .context("Could not generate summary")?,
+                    .context("Could not fetch near events")?;
 
-                "tooltip": today(&conn, calendars)
-                    .await
-                    .context("Could not generate full calendar")?,
-            });
+                let tasks = match with_tasks {
+                    true => {
+                        let (tasks, task_failures) =
+                            fetch_tasks(backend.as_ref(), &selection.without_whitelist()).await?;
+                        failures.extend(task_failures);
+                        tasks
+                    }
+                    false => vec![],
+                };
 
-            println!("{}", value.to_string());
+                println!(
+                    "{}",
+                    waybar(
+                        &near_events,
+                        &tasks,
+                        &failures,
+                        limit_to_today,
+                        with_all_day,
+                    )
+                );
+            }
         }
     }
 
     Ok(())
 }
 
+// Returns the backend to read the calendars from, which is the EDS unless other sources
+// were given, along with the feeds.
+fn open_backend<'a>(
+    conn: &'a anyhow::Result<zbus::Connection>,
+    sources: &[config::Source],
+    feeds: &[config::Source],
+) -> anyhow::Result<Box<dyn backend::Backend + 'a>> {
+    let mut backends: Vec<Box<dyn backend::Backend + 'a>> = match sources.is_empty() {
+        true => vec![Box::new(eds::backend::Eds::new(
+            conn.as_ref().map_err(|err| anyhow::anyhow!("{:#}", err))?,
+        ))],
+        false => sources
+            .iter()
+            .map(|source| source.backend())
+            .collect::<anyhow::Result<_>>()?,
+    };
+    for feed in feeds.iter() {
+        backends.push(feed.backend()?);
+    }
+
+    Ok(match backends.len() {
+        1 => backends.remove(0),
+        _ => Box::new(backend::Combined::new(backends)),
+    })
+}
+
+// Returns a connection to the session bus, which the EDS is reached on.
+async fn connect() -> anyhow::Result<zbus::Connection> {
+    zbus::connection::Builder::session()
+        .context("Could not build dbus session")?
+        .build()
+        .await
+        .context("Could not connect to session dbus")
+}
+
 // Print a list of all the known calendars.
-async fn calendars(conn: &zbus::Connection) -> anyhow::Result<()> {
-    let calendars = fetch_calendars(conn).await?;
-
-    for cal in calendars.iter() {
-        println!(
-            "{}",
-            match &cal.display_name {
-                Some(name) => name,
-                None => "Unknown",
-            }
-        );
+async fn calendars(
+    conn: &zbus::Connection,
+    selection: &Selection,
+    json: bool,
+) -> anyhow::Result<()> {
+    let calendars = selected_sources(conn, eds::calendar::SourceKind::Calendar, selection).await?;
+
+    // Opening every calendar takes a while, and fails for the accounts that are offline,
+    // so, they are opened all at once, and the calendars it fails on, or that do not
+    // respond in time, are reported as unknown.
+    let (found, _) = fetch_each(&calendars, |calendar| async move {
+        Ok((calendar.uid.as_str(), calendar.is_writable().await?))
+    })
+    .await;
+    let found: std::collections::HashMap<_, _> = found.into_iter().collect();
+    let writable: Vec<_> = calendars
+        .iter()
+        .map(|calendar| found.get(calendar.uid.as_str()).copied())
+        .collect();
+
+    if json {
+        let calendars: Vec<_> = calendars
+            .iter()
+            .zip(writable)
+            .map(|(cal, writable)| {
+                serde_json::json!({
+                    "uid": cal.uid,
+                    "name": cal.display_name,
+                    "parent": cal.parent,
+                    "account": cal.account,
+                    "backend": cal.backend,
+                    "color": cal.color,
+                    "enabled": cal.enabled,
+                    "selected": cal.selected,
+                    "writable": writable,
+                })
+            })
+            .collect();
+
+        println!("{}", serde_json::Value::Array(calendars));
+        return Ok(());
     }
 
+    let rows: Vec<[String; 8]> = calendars
+        .iter()
+        .zip(writable)
+        .map(|(cal, writable)| {
+            [
+                cal.uid.clone(),
+                cal.display_name.clone().unwrap_or("Unknown".to_owned()),
+                cal.account.clone().unwrap_or("-".to_owned()),
+                cal.backend.clone().unwrap_or("-".to_owned()),
+                cal.color.clone().unwrap_or("-".to_owned()),
+                match cal.enabled {
+                    true => "yes".to_owned(),
+                    false => "no".to_owned(),
+                },
+                match cal.selected {
+                    true => "yes".to_owned(),
+                    false => "no".to_owned(),
+                },
+                match writable {
+                    Some(true) => "yes".to_owned(),
+                    Some(false) => "no".to_owned(),
+                    None => "?".to_owned(),
+                },
+            ]
+        })
+        .collect();
+
+    println!(
+        "{}",
+        table(
+            [
+                "UID", "Name", "Account", "Backend", "Color", "Enabled", "Selected", "Writable"
+            ],
+            &rows
+        )
+    );
+
     Ok(())
 }
 
+// Returns the rows as a table with the columns padded to line up.
+fn table<const N: usize>(header: [&str; N], rows: &[[String; N]]) -> String {
+    let mut widths = header.map(|title| title.chars().count());
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    std::iter::once(header.map(|title| title.to_owned()))
+        .chain(rows.iter().cloned())
+        .map(|row| {
+            row.iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Applies the changes to an event, or to the occurrences of it picked by the scope.
+async fn edit(
+    conn: &zbus::Connection,
+    uid: &str,
+    calendar: Option<&str>,
+    occurrence: Option<chrono::DateTime<chrono::Local>>,
+    scope: Option<eds::calendar::ModType>,
+    mut changes: eds::event::EventChanges,
+) -> anyhow::Result<()> {
+    let (calendar, components) = locate_event(conn, uid, calendar).await?;
+    let master_tree = components.first().context("Could not find event")?;
+    let master = &master_tree.components[0];
+    let scope = resolve_scope(master, occurrence, scope)?;
+
+    let vevent = match (scope, occurrence) {
+        (eds::calendar::ModType::All, occurrence) => {
+            // The times are given for the occurrence, so, the whole series is moved
+            // by the same amount instead.
+            if let Some(occurrence) = occurrence {
+                let moved = changes.starts.unwrap_or(occurrence);
+
+                if let Some(ends) = changes.ends.take() {
+                    changes.duration = Some(ends - moved);
+                }
+
+                if let Some(starts) = eds::event::Event::from(master).starts {
+                    changes.starts =
+                        Some(starts.with_timezone(&chrono::Local) + (moved - occurrence));
+                }
+            }
+
+            let mut vevent = master_tree.clone();
+            changes.apply(&mut vevent.components[0])?;
+            vevent
+        }
+
+        (scope, Some(occurrence)) => {
+            let this_and_future = scope == eds::calendar::ModType::ThisAndFuture;
+            let rid = eds::event::recurrence_id(master, occurrence.to_utc())?;
+
+            // If the occurrence was already changed before, continue from there.
+            let mut vevent = match components.iter().find(|c| {
+                eds::event::component_recurrence_id(&c.components[0]).as_deref() == Some(&rid)
+            }) {
+                Some(vevent) => vevent.clone(),
+                None => {
+                    let mut vevent = master_tree.clone();
+                    vevent.components[0] =
+                        eds::event::detach_instance(master, occurrence.to_utc(), this_and_future)?;
+                    vevent
+                }
+            };
+
+            changes.apply(&mut vevent.components[0])?;
+            vevent
+        }
+
+        (_, None) => anyhow::bail!("An occurrence is required to change only some of them"),
+    };
+
+    calendar.modify_event(&vevent, scope).await
+}
+
+// Deletes an event, or the occurrences of it picked by the scope.
+async fn remove(
+    conn: &zbus::Connection,
+    uid: &str,
+    calendar: Option<&str>,
+    occurrence: Option<chrono::DateTime<chrono::Local>>,
+    scope: Option<eds::calendar::ModType>,
+) -> anyhow::Result<()> {
+    let (calendar, components) = locate_event(conn, uid, calendar).await?;
+    let master = &components
+        .first()
+        .context("Could not find event")?
+        .components[0];
+    let scope = resolve_scope(master, occurrence, scope)?;
+
+    let rid = match occurrence {
+        Some(occurrence) => Some(eds::event::recurrence_id(master, occurrence.to_utc())?),
+        None => None,
+    };
+
+    calendar.remove_event(uid, rid.as_deref(), scope).await
+}
+
+// Returns the occurrences of an event that a change should apply to.
+fn resolve_scope(
+    master: &calcard::icalendar::ICalendarComponent,
+    occurrence: Option<chrono::DateTime<chrono::Local>>,
+    scope: Option<eds::calendar::ModType>,
+) -> anyhow::Result<eds::calendar::ModType> {
+    match (master.is_recurrent(), occurrence, scope) {
+        (false, Some(_), _) => anyhow::bail!("The event does not recur, so, it has no occurrences"),
+        (_, None, Some(eds::calendar::ModType::This | eds::calendar::ModType::ThisAndFuture)) => {
+            anyhow::bail!("An occurrence is required to change only some of them")
+        }
+        (_, Some(_), None) => Ok(eds::calendar::ModType::This),
+        (_, _, scope) => Ok(scope.unwrap_or(eds::calendar::ModType::All)),
+    }
+}
+
+// Returns the calendar with the event and the component trees of the event. If the
+// calendar is not given, all the calendars are looked up for the event.
+async fn locate_event<'a>(
+    conn: &'a zbus::Connection,
+    uid: &str,
+    calendar: Option<&str>,
+) -> anyhow::Result<(
+    eds::calendar::Calendar<'a>,
+    Vec<calcard::icalendar::ICalendar>,
+)> {
+    let calendars = match calendar {
+        Some(name_or_uid) => vec![find_calendar(conn, name_or_uid).await?],
+        None => fetch_calendars(conn).await?,
+    };
+
+    for calendar in calendars {
+        if let Ok(components) = calendar.fetch_components(uid).await
+            && !components.is_empty()
+        {
+            return Ok((calendar, components));
+        }
+    }
+
+    anyhow::bail!("Could not find an event with the UID \"{}\"", uid)
+}
+
+// Returns the Waybar custom module output for the near events.
+// https://man.archlinux.org/man/extra/waybar/waybar-custom.5.en#RETURN-TYPE
+// The module is marked with the "degraded" class when some of the calendars failed.
+fn waybar(
+    near_events: &[eds::event::Event],
+    tasks: &[eds::task::Task],
+    failures: &[Failure],
+    limit_to_today: bool,
+    with_all_day: bool,
+) -> serde_json::Value {
+    if failures.is_empty() {
+        return serde_json::json!({
+            "text": summarize(near_events, tasks, limit_to_today, with_all_day),
+            "tooltip": tabulate(near_events, tasks),
+        });
+    }
+
+    let failed = failures
+        .iter()
+        .map(|failure| failure.calendar.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    serde_json::json!({
+        "text": summarize(near_events, tasks, limit_to_today, with_all_day),
+        "tooltip": format!("{}\nCould not fetch {}", tabulate(near_events, tasks), failed),
+        "class": "degraded",
+    })
+}
+
 // Returns the status of the current or upcoming events.
 async fn summary(
-    conn: &zbus::Connection,
-    whitelist: Option<Vec<String>>,
+    backend: &dyn backend::Backend,
+    selection: &Selection,
     limit_to_today: bool,
+    with_all_day: bool,
+    with_tasks: bool,
 ) -> anyhow::Result<String> {
-    let near_events = near_events(conn, whitelist).await?;
+    let (near_events, mut failures) = near_events(backend, selection).await?;
+
+    let tasks = match with_tasks {
+        true => {
+            let (tasks, task_failures) =
+                fetch_tasks(backend, &selection.without_whitelist()).await?;
+            failures.extend(task_failures);
+            tasks
+        }
+        false => vec![],
+    };
+
+    report_failures(&failures);
+    Ok(summarize(
+        &near_events,
+        &tasks,
+        limit_to_today,
+        with_all_day,
+    ))
+}
 
+// Returns the status of the current or upcoming events from the near events, followed
+// by the number of tasks due today, if there are any.
+fn summarize(
+    near_events: &[eds::event::Event],
+    tasks: &[eds::task::Task],
+    limit_to_today: bool,
+    with_all_day: bool,
+) -> String {
+    let summary = summarize_events(near_events, limit_to_today, with_all_day);
+
+    match tasks_due_today(tasks) {
+        Some(due) => format!("{}, {}", summary, due),
+        None => summary,
+    }
+}
+
+// Returns the status of the current or upcoming events from the near events. The all-day
+// events are left out unless asked for, they would always be ongoing otherwise.
+fn summarize_events(
+    near_events: &[eds::event::Event],
+    limit_to_today: bool,
+    with_all_day: bool,
+) -> String {
     // Filter out events that do not have a start and an end date.
     // Filter out events that were completed in the past.
     let now = chrono::Local::now().with_timezone(&rrule::Tz::Local(chrono::Local));
     let active_events: Vec<_> = near_events
         .iter()
+        .filter(|event| with_all_day || !event.all_day)
         .filter_map(|event| match (event.starts, event.ends) {
             (Some(starts), Some(ends)) if ends > now => Some((starts, ends, event)),
             _ => None,
@@ -147,37 +1188,34 @@ async fn summary(
         .filter(|(starts, ends, _)| starts <= &now && ends > &now)
         .collect();
 
-    if ongoing.len() > 0 {
-        return Ok(format!(
-            "{}",
-            ongoing
-                .iter()
-                .map(|(_, ends, event)| format!(
+    if !ongoing.is_empty() {
+        return ongoing
+            .iter()
+            .map(|(_, ends, event)| {
+                format!(
                     "{} ends in {}",
-                    event.title.clone().unwrap_or("Unknown Event".to_owned()),
+                    event.title.as_deref().unwrap_or("Unknown Event"),
                     utils::human_short_duration(ends.to_utc() - now.to_utc()),
-                ))
-                .collect::<Vec<String>>()
-                .join(", ")
-        ));
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
     }
 
     // If we are here, it means all events are upcoming.
-    let upcoming_events: Option<Vec<_>> = active_events.first().and_then(|(upcoming, _, _)| {
-        Some(
-            // Find a list of all the events that start at the same time as the
-            // next event.
-            active_events
-                .iter()
-                .filter(|(starts, _, _)| {
-                    upcoming.with_timezone(&chrono::Local) == starts.with_timezone(&chrono::Local)
-                })
-                .collect(),
-        )
+    let upcoming_events: Option<Vec<_>> = active_events.first().map(|(upcoming, _, _)| {
+        // Find a list of all the events that start at the same time as the
+        // next event.
+        active_events
+            .iter()
+            .filter(|(starts, _, _)| {
+                upcoming.with_timezone(&chrono::Local) == starts.with_timezone(&chrono::Local)
+            })
+            .collect()
     });
 
     if let Some(events) = upcoming_events
-        && events.len() > 0
+        && !events.is_empty()
     {
         let filtered: Vec<_> = if limit_to_today {
             events
@@ -197,38 +1235,98 @@ async fn summary(
                 .collect::<Vec<&str>>()
                 .join(", ");
 
-            return Ok(format!(
+            format!(
                 "{} in {}",
                 names,
                 utils::human_short_duration(starts.to_utc() - now.to_utc()),
-            ));
+            )
         } else {
-            return Ok("No Upcoming Event Today".to_owned());
+            "No Upcoming Event Today".to_owned()
         }
     } else {
-        return Ok("No Upcoming Events".to_owned());
+        "No Upcoming Events".to_owned()
     }
 }
 
 // Prints a list of all the events today.
-async fn today(conn: &zbus::Connection, whitelist: Option<Vec<String>>) -> anyhow::Result<String> {
-    let near_events = near_events(conn, whitelist).await?;
+async fn today(
+    backend: &dyn backend::Backend,
+    selection: &Selection,
+    json: bool,
+    with_tasks: bool,
+) -> anyhow::Result<String> {
+    let (near_events, mut failures) = near_events(backend, selection).await?;
+
+    if json {
+        report_failures(&failures);
 
-    // Filter for today.
+        let events = events_today(&near_events)
+            .iter()
+            .map(|event| event.to_json())
+            .collect();
+        return Ok(serde_json::Value::Array(events).to_string());
+    }
+
+    let tasks = match with_tasks {
+        true => {
+            let (tasks, task_failures) =
+                fetch_tasks(backend, &selection.without_whitelist()).await?;
+            failures.extend(task_failures);
+            tasks
+        }
+        false => vec![],
+    };
+
+    report_failures(&failures);
+    Ok(tabulate(&near_events, &tasks))
+}
+
+// Returns a table of all the events today from the near events, followed by the number
+// of tasks due today, if there are any.
+fn tabulate(near_events: &[eds::event::Event], tasks: &[eds::task::Task]) -> String {
+    let table = tabulate_events(near_events);
+
+    match tasks_due_today(tasks) {
+        Some(due) => format!("{}\n{}", table, due),
+        None => table,
+    }
+}
+
+// Returns the events that take place today from the near events, the all-day ones first.
+fn events_today(near_events: &[eds::event::Event]) -> Vec<&eds::event::Event> {
     let today = chrono::Local::now().date_naive();
-    let today_events = near_events
-        .into_iter()
-        .filter(|e| {
-            if let Some(starts) = e.starts {
-                starts.with_timezone(&chrono::Local).date_naive() == today
-            } else {
-                false
-            }
+
+    let (all_day, timed): (Vec<_>, Vec<_>) = near_events
+        .iter()
+        .filter(|e| match e.starts {
+            Some(_) if e.all_day => e.is_all_day_on(today),
+            Some(starts) => starts.with_timezone(&chrono::Local).date_naive() == today,
+            None => false,
         })
-        .collect::<Vec<eds::event::Event>>();
+        .partition(|e| e.all_day);
+
+    all_day.into_iter().chain(timed).collect()
+}
+
+// Returns a table of all the events today from the near events. The all-day events are
+// listed on a line of their own, above the ones that happen at a time.
+fn tabulate_events(near_events: &[eds::event::Event]) -> String {
+    let (all_day_events, today_events): (Vec<_>, Vec<_>) = events_today(near_events)
+        .into_iter()
+        .partition(|e| e.all_day);
+
+    if today_events.is_empty() && all_day_events.is_empty() {
+        return "No Events Today".to_owned();
+    }
+
+    let mut table = vec!["Today".to_owned()];
+    if !all_day_events.is_empty() {
+        let titles = all_day_events
+            .iter()
+            .map(|e| e.title.as_deref().unwrap_or("Unknown Event"))
+            .collect::<Vec<&str>>();
 
-    if today_events.len() == 0 {
-        return Ok("No Events Today".to_owned());
+        table.push(format!("All day: {}", titles.join(", ")));
     }
 
     // Put them in a table.
@@ -237,12 +1335,12 @@ async fn today(conn: &zbus::Connection, whitelist: Option<Vec<String>>) -> anyho
         .map(|item| {
             let starts = item
                 .starts
-                .map(|dt| utils::human_short_time(dt))
+                .map(utils::human_short_time)
                 .unwrap_or("?".to_owned());
 
             let ends = item
                 .ends
-                .map(|dt| utils::human_short_time(dt))
+                .map(utils::human_short_time)
                 .unwrap_or("?".to_owned());
 
             format!(
@@ -252,62 +1350,587 @@ async fn today(conn: &zbus::Connection, whitelist: Option<Vec<String>>) -> anyho
                 ends,
             )
         })
+        .collect::<Vec<_>>();
+
+    table.extend(lines);
+    table.join("\n")
+}
+
+// Prints a list of the open tasks.
+async fn tasks(
+    backend: &dyn backend::Backend,
+    selection: &Selection,
+    all: bool,
+) -> anyhow::Result<String> {
+    // Listing the tasks of every status is what "--all" is for.
+    let selection = match all {
+        true => selection.with_all_statuses(),
+        false => selection.clone(),
+    };
+
+    let (mut tasks, failures) = fetch_tasks(backend, &selection).await?;
+    report_failures(&failures);
+
+    if !all {
+        tasks.retain(|task| task.is_open());
+    }
+
+    if tasks.is_empty() {
+        return Ok("No Open Tasks".to_owned());
+    }
+
+    // The tasks that are due first come first, followed by the ones without a due time.
+    tasks.sort_by_key(|task| {
+        (
+            task.due.is_none(),
+            task.due.map(|due| due.to_utc()),
+            task.priority.filter(|priority| *priority > 0).unwrap_or(10),
+        )
+    });
+
+    let now = chrono::Local::now();
+    let lines = tasks
+        .iter()
+        .map(|task| {
+            let mut line = format!("• {}", task.title.as_deref().unwrap_or("Unknown Task"));
+
+            if let Some(due) = task.due {
+                line.push_str(&format!(", due {}", utils::human_short_date_time(due)));
+            }
+
+            if task.is_overdue(now) {
+                line.push_str(" (overdue)");
+            } else if !task.is_open() {
+                line.push_str(" (done)");
+            } else if let Some(percent) = task.percent_complete.filter(|p| *p > 0) {
+                line.push_str(&format!(" ({}% done)", percent));
+            }
+
+            line
+        })
         .collect::<Vec<_>>()
         .join("\n");
 
-    Ok(format!("Today\n{}", lines))
+    Ok(format!("Tasks\n{}", lines))
 }
 
-// Returns a list of near events.
-async fn near_events(
+// Prints the memos, along with their descriptions, newest first.
+async fn memos(
     conn: &zbus::Connection,
-    whitelist: Option<Vec<String>>,
-) -> anyhow::Result<Vec<eds::event::Event>> {
-    let mut calendars = fetch_calendars(conn).await?;
+    selection: &Selection,
+    search: Option<String>,
+) -> anyhow::Result<String> {
+    let memo_lists = selected_sources(conn, eds::calendar::SourceKind::MemoList, selection).await?;
 
-    // Apply the whitelist if necessary.
-    if let Some(whitelist) = whitelist {
-        calendars = calendars
-            .into_iter()
-            .filter(|c| match &c.display_name {
-                Some(name) => whitelist.contains(name),
-                _ => false,
-            })
-            .collect();
+    let (memos, failures) = fetch_each(&memo_lists, |memo_list| memo_list.fetch_memos()).await;
+    report_failures(&failures);
+
+    let mut memos: Vec<_> = memos.into_iter().flatten().collect();
+
+    if let Some(search) = search {
+        memos.retain(|memo| memo.matches(&search));
     }
 
-    let mut near_events = Vec::new();
-    for calendar in calendars.iter() {
-        let mut events = calendar
-            .fetch_near_events()
-            .await
-            .context("Could not fetch today events")?;
-        near_events.append(&mut events);
+    if memos.is_empty() {
+        return Ok("No Memos".to_owned());
     }
 
-    // Remove all events that are not happening.
-    // TODO: Ideally, we should check attendees and remove events that you declined.
-    near_events = near_events
+    memos.sort_by_key(|memo| std::cmp::Reverse(memo.starts.map(|starts| starts.to_utc())));
+
+    let entries = memos
+        .iter()
+        .map(|memo| {
+            let mut entry = format!("• {}", memo.title.as_deref().unwrap_or("Untitled Memo"));
+
+            if let Some(starts) = memo.starts {
+                entry.push_str(&format!(
+                    ", {}",
+                    starts.with_timezone(&chrono::Local).format("%a, %b %-d %Y")
+                ));
+            }
+
+            if !memo.categories.is_empty() {
+                entry.push_str(&format!(" [{}]", memo.categories.join(", ")));
+            }
+
+            // Indent the description so it reads as a part of the memo.
+            if let Some(description) = &memo.description {
+                for line in description.lines() {
+                    entry.push_str(&format!("\n  {}", line));
+                }
+            }
+
+            entry
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!("Memos\n{}", entries))
+}
+
+// Prints the events in the range with the text in any of the fields. The search is run
+// on the EDS, so, only the matching events are pulled and expanded.
+async fn search(
+    backend: &dyn backend::Backend,
+    selection: &Selection,
+    text: &str,
+    fields: &[eds::query::Field],
+    from: chrono::DateTime<chrono::Local>,
+    to: chrono::DateTime<chrono::Local>,
+) -> anyhow::Result<String> {
+    let fields = match fields.is_empty() {
+        true => &[eds::query::Field::Any][..],
+        false => fields,
+    };
+
+    let query = fields
+        .iter()
+        .map(|field| eds::query::Query::contains(*field, text))
+        .reduce(|query, other| query.or(other))
+        .unwrap_or(eds::query::Query::All);
+
+    let (events, failures) = events_between(backend, selection, from, to, &query).await?;
+    report_failures(&failures);
+
+    if events.is_empty() {
+        return Ok("No Matching Events".to_owned());
+    }
+
+    let lines = events
+        .iter()
+        .map(|event| {
+            let starts = event
+                .starts
+                .map(utils::human_short_date_time)
+                .unwrap_or("?".to_owned());
+
+            let ends = event
+                .ends
+                .map(utils::human_short_time)
+                .unwrap_or("?".to_owned());
+
+            format!(
+                "• {} @ {}-{}",
+                event.title.as_deref().unwrap_or("Unknown Event"),
+                starts,
+                ends,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!("Events\n{}", lines))
+}
+
+// Prints the alarms that go off from now until the duration passes.
+async fn alarms(
+    backend: &dyn backend::Backend,
+    selection: &Selection,
+    within: chrono::Duration,
+) -> anyhow::Result<String> {
+    let now = chrono::Utc::now();
+    let (alarms, failures) = upcoming_alarms(backend, selection, now, now + within, None).await?;
+    report_failures(&failures);
+
+    if alarms.is_empty() {
+        return Ok("No Upcoming Alarms".to_owned());
+    }
+
+    let lines = alarms
+        .iter()
+        .map(|(at, event, alarm)| {
+            let at = at.with_timezone(&rrule::Tz::Local(chrono::Local));
+
+            let starts = event
+                .starts
+                .map(utils::human_short_date_time)
+                .unwrap_or("?".to_owned());
+
+            format!(
+                "• {} {} @ {} ({})",
+                utils::human_short_date_time(at),
+                event.title.as_deref().unwrap_or("Unknown Event"),
+                starts,
+                alarm.action_name(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!("Alarms\n{}", lines))
+}
+
+// Returns a list of the free slots in the windows.
+fn free(
+    busy: &[freebusy::Interval],
+    windows: Vec<freebusy::Interval>,
+    duration: chrono::Duration,
+) -> String {
+    let slots = freebusy::free_slots(&windows, busy, duration);
+    if slots.is_empty() {
+        return "No Free Slots".to_owned();
+    }
+
+    let lines = slots
+        .iter()
+        .map(|(starts, ends)| {
+            let starts = starts.with_timezone(&rrule::Tz::Local(chrono::Local));
+            let ends = ends.with_timezone(&rrule::Tz::Local(chrono::Local));
+
+            format!(
+                "• {}-{} ({})",
+                utils::human_short_date_time(starts),
+                utils::human_short_time(ends),
+                utils::human_short_duration(ends.to_utc() - starts.to_utc()),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("Free\n{}", lines)
+}
+
+// Returns the alarms that go off in the range, in order, along with their events. If the
+// lead time is given, the events without any alarms get one at that time before they start.
+#[allow(clippy::type_complexity)]
+async fn upcoming_alarms(
+    backend: &dyn backend::Backend,
+    selection: &Selection,
+    starts: chrono::DateTime<chrono::Utc>,
+    ends: chrono::DateTime<chrono::Utc>,
+    lead: Option<chrono::Duration>,
+) -> anyhow::Result<(
+    Vec<(
+        chrono::DateTime<chrono::Utc>,
+        eds::event::Event,
+        eds::alarm::Alarm,
+    )>,
+    Vec<Failure>,
+)> {
+    // Without a lead time, only the events with alarms in the range matter, so, the EDS
+    // is left to pick them.
+    let query = match lead {
+        Some(_) => eds::query::Query::All,
+        None => eds::query::Query::has_alarms_in_range(starts, ends),
+    };
+
+    // The alarms usually go off a little before, or after, their events. So, the events
+    // are looked up from a day before the range to a week after it.
+    let (events, failures) = events_between(
+        backend,
+        selection,
+        (starts - chrono::Duration::days(1)).with_timezone(&chrono::Local),
+        (ends + chrono::Duration::weeks(1)).with_timezone(&chrono::Local),
+        &query,
+    )
+    .await?;
+
+    let mut alarms: Vec<_> = events
         .into_iter()
-        .filter(|e| match e.status {
-            Some(icalendar::ICalendarStatus::Tentative) => true,
-            Some(icalendar::ICalendarStatus::Confirmed) => true,
-            Some(icalendar::ICalendarStatus::Completed) => true,
-            Some(icalendar::ICalendarStatus::Final) => true,
-            Some(icalendar::ICalendarStatus::InProcess) => true,
-            _ => true,
+        .flat_map(|mut event| {
+            if event.alarms.is_empty()
+                && let Some(lead) = lead
+            {
+                event.alarms.push(eds::alarm::Alarm::before_start(lead));
+            }
+
+            event
+                .alarm_times()
+                .into_iter()
+                .filter(|(at, _)| *at >= starts && *at < ends)
+                .map(|(at, alarm)| (at, event.clone(), alarm.clone()))
+                .collect::<Vec<_>>()
         })
         .collect();
 
+    // The absolute alarms of recurring events are the same for every occurrence.
+    alarms.sort_by(|(a_at, a_event, _), (b_at, b_event, _)| {
+        a_at.cmp(b_at).then_with(|| a_event.uid.cmp(&b_event.uid))
+    });
+    alarms.dedup_by(|(a_at, a_event, a_alarm), (b_at, b_event, b_alarm)| {
+        a_at == b_at && a_event.uid == b_event.uid && a_alarm.trigger == b_alarm.trigger
+    });
+
+    Ok((alarms, failures))
+}
+
+// Returns a human summary of the number of open tasks due today.
+fn tasks_due_today(tasks: &[eds::task::Task]) -> Option<String> {
+    let today = chrono::Local::now().date_naive();
+    let due = tasks
+        .iter()
+        .filter(|task| task.is_open() && task.is_due_on(today))
+        .count();
+
+    match due {
+        0 => None,
+        1 => Some("1 task due today".to_owned()),
+        due => Some(format!("{} tasks due today", due)),
+    }
+}
+
+// Returns a list of all the tasks from the task lists after applying the selection, along
+// with the task lists that could not be fetched.
+async fn fetch_tasks(
+    backend: &dyn backend::Backend,
+    selection: &Selection,
+) -> anyhow::Result<(Vec<eds::task::Task>, Vec<Failure>)> {
+    let (task_lists, mut failures) = backend.reachable_task_lists().await;
+    let task_lists = select(task_lists, selection)?;
+
+    let (tasks, fetch_failures) =
+        fetch_each(&task_lists, |task_list| task_list.fetch_tasks()).await;
+    failures.extend(fetch_failures);
+
+    Ok((
+        tasks
+            .into_iter()
+            .flatten()
+            .filter(|task| selection.picks_task(task))
+            .collect(),
+        failures,
+    ))
+}
+
+// Returns the sources of the kind from the connection after applying the selection.
+async fn selected_sources<'a>(
+    conn: &'a zbus::Connection,
+    kind: eds::calendar::SourceKind,
+    selection: &Selection,
+) -> anyhow::Result<Vec<eds::calendar::Calendar<'a>>> {
+    let sources = eds::calendar::Calendar::fetch_all_of(conn, kind)
+        .await
+        .context("Could not list all sources")?;
+
+    select(sources, selection)
+}
+
+// Returns the calendars after applying the selection, in a stable order.
+fn select<C: backend::Calendar>(
+    mut sources: Vec<C>,
+    selection: &Selection,
+) -> anyhow::Result<Vec<C>> {
+    // Sort them so you have a stable order.
+    sources.sort_by(|a, b| a.name().cmp(&b.name()));
+
+    // Skip the ones that were turned off, unless they were asked for.
+    let (visible, hidden): (Vec<_>, Vec<_>) = sources
+        .into_iter()
+        .partition(|c| selection.include_hidden || c.is_visible());
+
+    let mut sources = visible;
+
+    // Apply the whitelist if necessary. A selector that picks nothing is most likely
+    // a typo, so, it is reported instead of silently leaving out the calendars.
+    if let Some(whitelist) = &selection.whitelist {
+        for selector in whitelist {
+            if sources.iter().any(|c| selector.matches(c)) {
+                continue;
+            }
+
+            if hidden.iter().any(|c| selector.matches(c)) {
+                anyhow::bail!(
+                    "\"{}\" only matches hidden calendars, use --include-hidden to use them",
+                    selector
+                );
+            }
+
+            anyhow::bail!("\"{}\" does not match any calendar", selector);
+        }
+
+        sources.retain(|c| whitelist.iter().any(|selector| selector.matches(c)));
+    }
+
+    // The exclusions apply to every kind of source, so, they are allowed to match nothing.
+    sources.retain(|c| !selection.exclude.iter().any(|selector| selector.matches(c)));
+
+    Ok(sources)
+}
+
+// Returns a list of near events, along with the calendars that could not be fetched.
+async fn near_events(
+    backend: &dyn backend::Backend,
+    selection: &Selection,
+) -> anyhow::Result<(Vec<eds::event::Event>, Vec<Failure>)> {
+    let (calendars, mut failures) = selected_calendars(backend, selection).await?;
+
+    let (events, fetch_failures) = fetch_each(&calendars, |calendar| async move {
+        Ok::<_, anyhow::Error>(found_on(calendar.fetch_near_events().await?, calendar))
+    })
+    .await;
+    failures.extend(fetch_failures);
+
+    Ok((
+        without_replied(
+            prepare_events(events.into_iter().flatten().collect(), selection),
+            selection,
+        ),
+        failures,
+    ))
+}
+
+// Returns a list of the events in the range that match the query, along with the
+// calendars that could not be fetched.
+async fn events_between(
+    backend: &dyn backend::Backend,
+    selection: &Selection,
+    starts: chrono::DateTime<chrono::Local>,
+    ends: chrono::DateTime<chrono::Local>,
+    query: &eds::query::Query,
+) -> anyhow::Result<(Vec<eds::event::Event>, Vec<Failure>)> {
+    let (calendars, mut failures) = selected_calendars(backend, selection).await?;
+
+    let (events, fetch_failures) = fetch_each(&calendars, |calendar| async move {
+        let events = calendar.fetch_events(starts, ends, query).await?;
+        Ok::<_, anyhow::Error>(found_on(events, calendar))
+    })
+    .await;
+    failures.extend(fetch_failures);
+
+    Ok((
+        prepare_events(events.into_iter().flatten().collect(), selection),
+        failures,
+    ))
+}
+
+// Returns the events, marked as found on the calendar.
+fn found_on<C: backend::Calendar + ?Sized>(
+    events: Vec<eds::event::Event>,
+    calendar: &C,
+) -> Vec<eds::event::Event> {
+    events
+        .into_iter()
+        .map(|event| {
+            event.on(eds::event::CalendarRef::new(
+                calendar.uid(),
+                calendar.name(),
+                calendar.addresses(),
+            ))
+        })
+        .collect()
+}
+
+// Runs the fetch on all the calendars at once. The calendars that fail, or do not respond
+// in time, are left out of the results and are returned as failures instead, so that
+// a single offline account does not take down the rest.
+async fn fetch_each<'c, C, T, F>(
+    calendars: &'c [C],
+    fetch: impl Fn(&'c C) -> F,
+) -> (Vec<T>, Vec<Failure>)
+where
+    C: backend::Calendar,
+    F: std::future::Future<Output = anyhow::Result<T>>,
+{
+    let results = futures_util::future::join_all(
+        calendars
+            .iter()
+            .map(|calendar| backend::with_timeout(fetch(calendar))),
+    )
+    .await;
+
+    let mut items = Vec::new();
+    let mut failures = Vec::new();
+    for (calendar, result) in calendars.iter().zip(results) {
+        match result {
+            Ok(item) => items.push(item),
+            Err(error) => failures.push(Failure {
+                calendar: calendar.name().unwrap_or(calendar.uid()).to_owned(),
+                error,
+            }),
+        }
+    }
+
+    (items, failures)
+}
+
+// Reports the calendars that could not be fetched, without getting in the way of the
+// output, which is still useful without them.
+fn report_failures(failures: &[Failure]) {
+    for failure in failures {
+        eprintln!(
+            "Could not fetch \"{}\": {:#}",
+            failure.calendar, failure.error
+        );
+    }
+}
+
+// Removes the events with the statuses that were not picked, like the cancelled ones, and
+// sorts the rest by start time.
+fn prepare_events(
+    mut events: Vec<eds::event::Event>,
+    selection: &Selection,
+) -> Vec<eds::event::Event> {
+    // Remove all events with the statuses that were not picked, like the cancelled ones.
+    events.retain(|e| selection.picks_event(e));
+
     // Sort all events by start time.
-    near_events.sort_by(|a, b| a.starts.cmp(&b.starts));
+    events.sort_by_key(|e| e.starts);
 
-    Ok(near_events)
+    events
+}
+
+// Removes the events the user replied to in one of the ways the selection hides, like
+// the ones they declined.
+fn without_replied(
+    mut events: Vec<eds::event::Event>,
+    selection: &Selection,
+) -> Vec<eds::event::Event> {
+    events.retain(|event| {
+        event
+            .reply(&selection.addresses)
+            .is_none_or(|reply| !selection.hidden_replies.contains(&reply))
+    });
+
+    events
+}
+
+// Returns the calendars from the backend after applying the selection, along with the
+// backends that could not list their calendars.
+async fn selected_calendars<'a>(
+    backend: &'a dyn backend::Backend,
+    selection: &Selection,
+) -> anyhow::Result<(Vec<Box<dyn backend::Calendar + 'a>>, Vec<Failure>)> {
+    let (calendars, failures) = backend.reachable_calendars().await;
+    Ok((select(calendars, selection)?, failures))
+}
+
+// Returns the calendar with the given UID or display name.
+async fn find_calendar<'a>(
+    conn: &'a zbus::Connection,
+    name_or_uid: &str,
+) -> anyhow::Result<eds::calendar::Calendar<'a>> {
+    let (by_uid, by_name): (Vec<_>, Vec<_>) = fetch_calendars(conn)
+        .await?
+        .into_iter()
+        .partition(|c| c.uid == name_or_uid);
+
+    if let Some(calendar) = by_uid.into_iter().next() {
+        return Ok(calendar);
+    }
+
+    let mut matches: Vec<_> = by_name
+        .into_iter()
+        .filter(|c| {
+            c.display_name.as_deref() == Some(name_or_uid)
+                || c.path().as_deref() == Some(name_or_uid)
+        })
+        .collect();
+
+    match matches.len() {
+        0 => anyhow::bail!("Could not find a calendar named \"{}\"", name_or_uid),
+        1 => Ok(matches.remove(0)),
+        _ => anyhow::bail!(
+            "There are multiple calendars named \"{}\", use the UID or \"account/name\" instead",
+            name_or_uid
+        ),
+    }
 }
 
 // Return a list of calendars from the connection.
-async fn fetch_calendars(conn: &zbus::Connection) -> anyhow::Result<Vec<eds::calendar::Calendar>> {
-    let mut calendars = eds::calendar::Calendar::fetch_all(&conn)
+async fn fetch_calendars(
+    conn: &zbus::Connection,
+) -> anyhow::Result<Vec<eds::calendar::Calendar<'_>>> {
+    let mut calendars = eds::calendar::Calendar::fetch_all(conn)
         .await
         .context("Could not list all calendars")?;
 
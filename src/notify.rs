@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use futures_util::StreamExt;
+use zbus::zvariant;
+
+use crate::{eds, utils};
+
+#[zbus::proxy(
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications",
+    interface = "org.freedesktop.Notifications"
+)]
+trait Notifications {
+    // Shows a notification, or replaces an existing one, and returns its id. The actions
+    // are a flat list of action keys, each followed by its label.
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, zvariant::Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    // Emitted when one of the actions on a notification was picked. This is broadcast,
+    // so, it is also emitted for the notifications from the other applications.
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+
+    // Emitted when a notification was dismissed, expired or closed.
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+// The details of an event that a notification reminds of.
+#[derive(Debug, Clone)]
+struct Reminder {
+    title: String,
+    starts: Option<chrono::DateTime<rrule::Tz>>,
+    ends: Option<chrono::DateTime<rrule::Tz>>,
+    link: Option<String>,
+}
+
+impl From<&eds::event::Event> for Reminder {
+    fn from(event: &eds::event::Event) -> Self {
+        Self {
+            title: event
+                .title
+                .clone()
+                .unwrap_or_else(|| "Unknown Event".to_owned()),
+            starts: event.starts,
+            ends: event.ends,
+            link: meeting_link(event),
+        }
+    }
+}
+
+impl Reminder {
+    // Returns the body of the notification, relative to the current time.
+    fn body(&self) -> String {
+        let now = chrono::Utc::now();
+
+        let Some(starts) = self.starts else {
+            return String::new();
+        };
+
+        let when = if starts.to_utc() > now {
+            format!(
+                "starts in {}",
+                utils::human_short_duration(starts.to_utc() - now)
+            )
+        } else {
+            "started".to_owned()
+        };
+
+        match self.ends {
+            Some(ends) => format!(
+                "{}-{}, {}",
+                utils::human_short_time(starts),
+                utils::human_short_time(ends),
+                when,
+            ),
+            None => format!("{}, {}", utils::human_short_time(starts), when),
+        }
+    }
+}
+
+// Keeps running and shows a desktop notification every time an alarm of an event goes
+// off. The events without any alarms are reminded of at the lead time before they start,
+// if there is one. The calendars are checked again every minute, so, the changes to them
+// are picked up without a restart.
+pub async fn notify(
+    conn: &zbus::Connection,
+    selection: &crate::Selection,
+    lead: Option<chrono::Duration>,
+    snooze: chrono::Duration,
+) -> anyhow::Result<()> {
+    let proxy = NotificationsProxy::new(conn)
+        .await
+        .context("Could not build notifications proxy")?;
+
+    let mut invoked = proxy
+        .receive_action_invoked()
+        .await
+        .context("Could not subscribe to notification actions")?;
+
+    let mut closed = proxy
+        .receive_notification_closed()
+        .await
+        .context("Could not subscribe to closed notifications")?;
+
+    let backend = eds::backend::Eds::new(conn);
+
+    let snooze_label = format!("Snooze {}", utils::human_short_duration(snooze));
+
+    // The notifications that are still on the screen, by their id.
+    let mut shown: HashMap<u32, Reminder> = HashMap::new();
+    let mut snoozed: Vec<(chrono::DateTime<chrono::Utc>, Reminder)> = Vec::new();
+
+    // The alarms that went off before the daemon was started are not shown.
+    let mut checked = chrono::Utc::now();
+
+    loop {
+        let now = chrono::Utc::now();
+        let minute = chrono::Duration::minutes(1);
+
+        // The calendars that could not be fetched are tried again in the next round. So are
+        // all of them when the alarms could not be looked up at all, without losing the
+        // alarms that go off in the meantime.
+        let (alarms, fetched) =
+            match crate::upcoming_alarms(&backend, selection, checked, now + minute, lead).await {
+                Ok((alarms, _)) => (alarms, true),
+                Err(err) => {
+                    eprintln!("{:#}", err);
+                    (vec![], false)
+                }
+            };
+
+        let alarms = alarms
+            .into_iter()
+            // Only these actions make sense on a desktop.
+            .filter(|(_, _, alarm)| matches!(alarm.action_name(), "display" | "audio"))
+            .collect::<Vec<_>>();
+
+        let mut due: Vec<Reminder> = alarms
+            .iter()
+            .filter(|(at, _, _)| *at < now)
+            .map(|(_, event, _)| Reminder::from(event))
+            .collect();
+
+        due.extend(
+            snoozed
+                .extract_if(.., |(at, _)| *at < now)
+                .map(|(_, reminder)| reminder),
+        );
+
+        for reminder in due {
+            let mut actions = Vec::new();
+            if reminder.link.is_some() {
+                actions.extend(["join", "Join"]);
+            }
+            actions.extend(["snooze", snooze_label.as_str()]);
+
+            let id = proxy
+                .notify(
+                    "calcal",
+                    0,
+                    "x-office-calendar",
+                    &reminder.title,
+                    &reminder.body(),
+                    &actions,
+                    HashMap::new(),
+                    -1,
+                )
+                .await
+                .context("Could not show notification")?;
+
+            shown.insert(id, reminder);
+        }
+
+        // The alarms that are going off later are checked again in the next round.
+        if fetched {
+            checked = now;
+        }
+
+        let next = alarms
+            .iter()
+            .map(|(at, _, _)| *at)
+            .chain(snoozed.iter().map(|(at, _)| *at))
+            .filter_map(|at| (at - chrono::Utc::now()).to_std().ok())
+            .map(|wait| wait + std::time::Duration::from_millis(1))
+            .fold(utils::until_next_minute(), |next, wait| next.min(wait));
+
+        let tick = tokio::time::sleep(next);
+        tokio::pin!(tick);
+
+        loop {
+            tokio::select! {
+                _ = &mut tick => break,
+
+                Some(signal) = invoked.next() => {
+                    let args = signal.args()?;
+                    let Some(reminder) = shown.get(args.id()) else {
+                        continue;
+                    };
+
+                    match args.action_key().as_str() {
+                        "join" => {
+                            if let Some(link) = &reminder.link {
+                                open_link(link);
+                            }
+                        }
+                        "snooze" => {
+                            snoozed.push((chrono::Utc::now() + snooze, reminder.clone()));
+                        }
+                        _ => {}
+                    }
+                }
+
+                Some(signal) = closed.next() => {
+                    shown.remove(signal.args()?.id());
+                }
+            }
+        }
+    }
+}
+
+// Returns the link to join the meeting of the event. That is its URL, or, the first link
+// in its location, or in its description, whichever is found first.
+fn meeting_link(event: &eds::event::Event) -> Option<String> {
+    [&event.url, &event.location, &event.description]
+        .into_iter()
+        .find_map(|text| first_link(text.as_deref()?))
+}
+
+fn first_link(text: &str) -> Option<String> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"'))
+        .find(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .map(|link| link.to_owned())
+}
+
+// Opens the link with the default application. Failing to open it should not stop the
+// notifications, so, the error is only reported. The opener is waited on in the
+// background, so that it does not linger as a zombie for as long as the daemon runs.
+fn open_link(link: &str) {
+    let mut child = match tokio::process::Command::new("xdg-open").arg(link).spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            eprintln!("Could not open \"{}\": {}", link, err);
+            return;
+        }
+    };
+
+    let link = link.to_owned();
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => {
+                eprintln!("Could not open \"{}\": xdg-open {}", link, status)
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("Could not open \"{}\": {}", link, err),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(properties: &str) -> eds::event::Event {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:standup\r\nDTSTART:20250106T100000Z\r\n\
+             {}END:VEVENT\r\nEND:VCALENDAR\r\n",
+            properties.replace('\n', "\r\n"),
+        );
+        let trees = eds::calendar::parse_components(
+            &ics,
+            &calcard::icalendar::ICalendarComponentType::VEvent,
+        );
+
+        eds::event::Event::from_tree(&trees[0])
+    }
+
+    #[test]
+    fn prefers_the_url_of_the_event() {
+        let event = event(
+            "URL:https://meet.example.com/url\nLOCATION:https://meet.example.com/location\n\
+             DESCRIPTION:Join at https://meet.example.com/description\n",
+        );
+        assert_eq!(
+            meeting_link(&event).as_deref(),
+            Some("https://meet.example.com/url")
+        );
+    }
+
+    #[test]
+    fn finds_the_link_in_the_location() {
+        let event = event(
+            "LOCATION:Room 4 or https://meet.example.com/location\n\
+             DESCRIPTION:Join at https://meet.example.com/description\n",
+        );
+        assert_eq!(
+            meeting_link(&event).as_deref(),
+            Some("https://meet.example.com/location")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_link_in_the_description() {
+        let event =
+            event("LOCATION:Room 4\nDESCRIPTION:Join at <https://meet.example.com/description>\n");
+        assert_eq!(
+            meeting_link(&event).as_deref(),
+            Some("https://meet.example.com/description")
+        );
+    }
+
+    #[test]
+    fn finds_no_link_when_there_is_none() {
+        let event = event("LOCATION:Room 4\nDESCRIPTION:Bring snacks\n");
+        assert_eq!(meeting_link(&event), None);
+    }
+}
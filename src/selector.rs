@@ -0,0 +1,73 @@
+use anyhow::Context;
+
+use crate::backend;
+
+// Picks calendars by their UID, their name, or their "account/name" path. The names
+// and the paths can also be matched with a glob, like "Work/*", or, with a regex when
+// it is prefixed with "re:", like "re:^(Work|Team)/".
+#[derive(Debug, Clone)]
+pub struct Selector {
+    text: String,
+    pattern: Pattern,
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Exact(String),
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
+}
+
+impl std::str::FromStr for Selector {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        let pattern = if let Some(regex) = text.strip_prefix("re:") {
+            Pattern::Regex(
+                regex::Regex::new(regex)
+                    .with_context(|| format!("Could not parse \"{}\" as a regex", regex))?,
+            )
+        } else if text.contains(['*', '?', '[']) {
+            Pattern::Glob(
+                glob::Pattern::new(text)
+                    .with_context(|| format!("Could not parse \"{}\" as a glob", text))?,
+            )
+        } else {
+            Pattern::Exact(text.to_owned())
+        };
+
+        Ok(Self {
+            text: text.to_owned(),
+            pattern,
+        })
+    }
+}
+
+impl std::fmt::Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl Selector {
+    // Returns whether the calendar is picked by this selector.
+    pub fn matches<C: backend::Calendar + ?Sized>(&self, calendar: &C) -> bool {
+        // The UID is only ever matched exactly, the patterns are meant for the names.
+        if let Pattern::Exact(text) = &self.pattern
+            && text == calendar.uid()
+        {
+            return true;
+        }
+
+        calendar
+            .name()
+            .map(|name| name.to_owned())
+            .into_iter()
+            .chain(calendar.path())
+            .any(|name| match &self.pattern {
+                Pattern::Exact(text) => *text == name,
+                Pattern::Glob(glob) => glob.matches(&name),
+                Pattern::Regex(regex) => regex.is_match(&name),
+            })
+    }
+}
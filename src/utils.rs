@@ -1,3 +1,5 @@
+use anyhow::Context;
+
 // Normalizes a timezone string value.
 pub fn normalize_timezone(tz: &str) -> &str {
     if tz.starts_with('/') {
@@ -25,3 +27,174 @@ pub fn human_short_time(dt: chrono::DateTime<rrule::Tz>) -> String {
         .format("%-l:%M%P")
         .to_string()
 }
+
+// Returns the duration until the start of the next minute.
+pub fn until_next_minute() -> std::time::Duration {
+    let now = chrono::Local::now();
+    let elapsed = now.timestamp_subsec_millis() as u64 + now.timestamp() as u64 % 60 * 1000;
+    std::time::Duration::from_millis(60_000 - elapsed)
+}
+
+// Parses a local date time from the formats that are convenient to type on a terminal.
+// For example, "2025-08-20 14:00", "2025-08-20T14:00:00+05:30", "2025-08-20" or "14:00",
+// which is assumed to be today.
+pub fn parse_local_datetime(value: &str) -> anyhow::Result<chrono::DateTime<chrono::Local>> {
+    let value = value.trim();
+
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&chrono::Local));
+    }
+
+    let naive = [
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_time(chrono::NaiveTime::default()))
+    })
+    .or_else(|| {
+        ["%H:%M", "%H:%M:%S"]
+            .iter()
+            .find_map(|format| chrono::NaiveTime::parse_from_str(value, format).ok())
+            .map(|time| chrono::Local::now().date_naive().and_time(time))
+    });
+
+    naive
+        .and_then(|naive| naive.and_local_timezone(chrono::Local).earliest())
+        .with_context(|| format!("Could not parse \"{}\" as a date time", value))
+}
+
+// Returns a short human formatted time, prefixed by the date unless it is today.
+pub fn human_short_date_time(dt: chrono::DateTime<rrule::Tz>) -> String {
+    let local = dt.with_timezone(&chrono::Local);
+
+    if local.date_naive() == chrono::Local::now().date_naive() {
+        format!("today {}", human_short_time(dt))
+    } else {
+        format!("{} {}", local.format("%a, %b %-d"), human_short_time(dt))
+    }
+}
+
+// Parses a range of time in a day, like "9:00-17:00".
+pub fn parse_time_range(value: &str) -> anyhow::Result<(chrono::NaiveTime, chrono::NaiveTime)> {
+    let (starts, ends) = value
+        .split_once('-')
+        .with_context(|| format!("Could not parse \"{}\" as a range of time", value))?;
+
+    let parse = |time: &str| {
+        chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M")
+            .with_context(|| format!("Could not parse \"{}\" as a time", time))
+    };
+
+    let (starts, ends) = (parse(starts)?, parse(ends)?);
+    if starts >= ends {
+        anyhow::bail!("The range of time \"{}\" should end after it starts", value);
+    }
+
+    Ok((starts, ends))
+}
+
+// Returns the range from the start of yesterday to the start of the day after tomorrow.
+pub fn near_range() -> anyhow::Result<(
+    chrono::DateTime<chrono::Local>,
+    chrono::DateTime<chrono::Local>,
+)> {
+    let now = chrono::Local::now();
+
+    let starts = now
+        .with_time(chrono::NaiveTime::default())
+        .earliest()
+        .and_then(|dt| dt.checked_sub_days(chrono::Days::new(1)))
+        .context("Could not determine start of yesterday")?;
+
+    let ends = now
+        .with_time(chrono::NaiveTime::default())
+        .earliest()
+        .and_then(|dt| dt.checked_add_days(chrono::Days::new(2)))
+        .context("Could not determine the start of day after tomorrow")?;
+
+    Ok((starts, ends))
+}
+
+// Returns the path with a leading "~" replaced by the home directory.
+pub fn expand_home(path: &std::path::Path) -> std::path::PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => std::path::PathBuf::from(home).join(rest),
+        _ => path.to_owned(),
+    }
+}
+
+// Returns the directory calcal keeps its cached files in, following the XDG base
+// directory spec.
+pub fn cache_dir() -> Option<std::path::PathBuf> {
+    let dir = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| Some(std::path::PathBuf::from(std::env::var_os("HOME")?).join(".cache")))?;
+
+    Some(dir.join("calcal"))
+}
+
+// Returns the path of a file in the cache directory, named after the key, like a URL.
+// The name is a hash of the whole key, so that the keys that only differ in the
+// characters a file name cannot hold still get files of their own. The hash is keyed
+// the same way every time, so that the file is found again on the next run.
+pub fn cache_path(dir: &str, key: &str, extension: &str) -> Option<std::path::PathBuf> {
+    let hash = siphasher::sip128::SipHasher13::new().hash(key.as_bytes());
+
+    Some(
+        cache_dir()?
+            .join(dir)
+            .join(format!("{:032x}.{}", hash.as_u128(), extension)),
+    )
+}
+
+// Writes the file, creating the directories it is in as necessary.
+pub fn write_file(path: &std::path::Path, contents: &[u8]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create \"{}\"", dir.display()))?;
+    }
+
+    std::fs::write(path, contents)
+        .with_context(|| format!("Could not write \"{}\"", path.display()))
+}
+
+// How long a server gets to answer a request. It is shorter than the time a calendar
+// gets to respond, so that a feed that stops answering still falls back to its copy.
+const HTTP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(8);
+const HTTP_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+// Returns the client to make the HTTP requests with.
+pub fn http_client() -> anyhow::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(concat!("calcal/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .context("Could not build the HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gives_the_keys_files_of_their_own() {
+        let path = |key| cache_path("feeds", key, "json").unwrap();
+
+        assert_ne!(path("https://x/on-call.ics"), path("https://x/on_call.ics"));
+        assert_ne!(path("https://x/a?b"), path("https://x/a/b"));
+        assert_eq!(path("https://x/on-call.ics"), path("https://x/on-call.ics"));
+        assert_eq!(
+            path("https://x/on-call.ics").extension(),
+            Some("json".as_ref())
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::Context;
+use futures_util::future;
+
+use crate::{
+    backend::{self, Calendar as _},
+    eds,
+};
+
+// Keeps printing the output rendered from the near events every time it changes.
+// The events are tracked using live views on the calendars, so, they are only
+// fetched again when the day changes. The output is rendered again whenever a
+// calendar reports a change, a minute boundary passes or an event starts or ends.
+// The tasks from all the task lists are tracked the same way when requested. The
+// calendars that could not be watched are passed on to the render, and are tried
+// again every time the output could change.
+pub async fn watch<F>(
+    conn: &zbus::Connection,
+    selection: &crate::Selection,
+    with_tasks: bool,
+    render: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(&[eds::event::Event], &[eds::task::Task], &[crate::Failure]) -> String,
+{
+    let mut last_output: Option<String> = None;
+
+    loop {
+        let calendars =
+            crate::selected_sources(conn, eds::calendar::SourceKind::Calendar, selection).await?;
+
+        let task_lists = match with_tasks {
+            true => {
+                crate::selected_sources(
+                    conn,
+                    eds::calendar::SourceKind::TaskList,
+                    &selection.without_whitelist(),
+                )
+                .await?
+            }
+            false => vec![],
+        };
+
+        let mut views: Vec<_> = calendars.iter().map(|_| None).collect();
+        let mut task_views: Vec<_> = task_lists.iter().map(|_| None).collect();
+
+        let mut failures = open_missing(&calendars, &mut views, |calendar| {
+            calendar.view_near_events()
+        })
+        .await;
+        failures.extend(
+            open_missing(&task_lists, &mut task_views, |task_list| {
+                task_list.view_tasks()
+            })
+            .await,
+        );
+
+        // The views only cover the days around the day they were opened on.
+        let day = chrono::Local::now().date_naive();
+        while chrono::Local::now().date_naive() == day {
+            let events = crate::without_replied(
+                crate::prepare_events(
+                    views.iter().flatten().flat_map(|v| v.events()).collect(),
+                    selection,
+                ),
+                selection,
+            );
+
+            let tasks: Vec<_> = task_views
+                .iter()
+                .flatten()
+                .flat_map(|v| v.tasks())
+                .filter(|task| selection.picks_task(task))
+                .collect();
+
+            let output = render(&events, &tasks, &failures);
+            if last_output.as_ref() != Some(&output) {
+                println!("{}", output);
+                last_output = Some(output);
+            }
+
+            let tick = tokio::time::sleep(until_next_change(&events));
+            let mut open: Vec<_> = views
+                .iter_mut()
+                .chain(task_views.iter_mut())
+                .flatten()
+                .collect();
+            if open.is_empty() {
+                tick.await;
+            } else {
+                tokio::select! {
+                    _ = tick => {},
+                    (result, _, _) = future::select_all(open.iter_mut().map(|v| Box::pin(v.changed()))) => {
+                        result.context("Could not receive calendar changes")?;
+                        continue;
+                    },
+                }
+            }
+
+            // Only the calendars that failed are opened again, the views that work are kept.
+            if !failures.is_empty() {
+                failures = open_missing(&calendars, &mut views, |calendar| {
+                    calendar.view_near_events()
+                })
+                .await;
+                failures.extend(
+                    open_missing(&task_lists, &mut task_views, |task_list| {
+                        task_list.view_tasks()
+                    })
+                    .await,
+                );
+            }
+        }
+
+        // A view that cannot be closed is left to the EDS, which drops it along with the
+        // connection, so, it does not stop the output.
+        for view in views.into_iter().chain(task_views).flatten() {
+            if let Err(err) = view.close().await {
+                eprintln!("{:#}", err);
+            }
+        }
+    }
+}
+
+// Opens the views on the calendars that do not have one yet, all at once, and returns the
+// calendars that could not be opened, or did not respond in time.
+async fn open_missing<'a, 'c, F, Fut>(
+    calendars: &'c [eds::calendar::Calendar<'a>],
+    views: &mut [Option<eds::calendar::View<'a>>],
+    open: F,
+) -> Vec<crate::Failure>
+where
+    F: Fn(&'c eds::calendar::Calendar<'a>) -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<eds::calendar::View<'a>>>,
+{
+    let missing: Vec<_> = views
+        .iter_mut()
+        .zip(calendars)
+        .filter(|(view, _)| view.is_none())
+        .collect();
+
+    let results = future::join_all(
+        missing
+            .iter()
+            .map(|(_, calendar)| backend::with_timeout(open(calendar))),
+    )
+    .await;
+
+    let mut failures = Vec::new();
+    for ((view, calendar), result) in missing.into_iter().zip(results) {
+        match result {
+            Ok(opened) => *view = Some(opened),
+            Err(error) => failures.push(crate::Failure {
+                calendar: calendar.name().unwrap_or(calendar.uid()).to_owned(),
+                error,
+            }),
+        }
+    }
+
+    failures
+}
+
+// Returns the duration until the output could change next. That is, the next minute
+// boundary, or, the next time the whole minutes left until an event starts or ends
+// drops, whichever comes first.
+fn until_next_change(events: &[eds::event::Event]) -> std::time::Duration {
+    let now = chrono::Utc::now();
+    let minute = std::time::Duration::from_secs(60);
+
+    events
+        .iter()
+        .flat_map(|event| [event.starts, event.ends])
+        .flatten()
+        .filter_map(|dt| (dt.to_utc() - now).to_std().ok())
+        .map(|delta| {
+            let remainder = delta.as_millis() % minute.as_millis();
+            std::time::Duration::from_millis(remainder as u64 + 1)
+        })
+        .fold(crate::utils::until_next_minute(), |next, delta| {
+            next.min(delta)
+        })
+}
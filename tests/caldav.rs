@@ -0,0 +1,302 @@
+// Runs calcal end to end against a stand-in for a CalDAV server, which serves a principal,
+// a calendar home and a calendar from memory, the way Radicale lays them out.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+const CALENDAR: &str = "/dav/calendars/me/work/";
+
+struct Server {
+    url: String,
+    dir: PathBuf,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    // The objects on the calendar, by their path.
+    objects: BTreeMap<String, String>,
+    // The objects removed since the sync token was handed out.
+    removed: Vec<String>,
+    // The bodies of the REPORT requests, in order.
+    reports: Vec<String>,
+}
+
+impl Server {
+    // Serves the events on a port of its own, one request per connection.
+    fn start(events: &[(&str, &str, chrono::DateTime<chrono::Utc>)]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dav/", listener.local_addr().unwrap());
+
+        let dir = std::env::temp_dir().join(format!(
+            "calcal-caldav-{}-{}",
+            std::process::id(),
+            INSTANCES.fetch_add(1, Ordering::SeqCst),
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut state = State::default();
+        for (uid, title, starts) in events {
+            state.objects.insert(
+                format!("{}{}.ics", CALENDAR, uid),
+                event(uid, title, *starts),
+            );
+        }
+        let state = Arc::new(Mutex::new(state));
+
+        let shared = state.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    break;
+                };
+                let state = shared.clone();
+                std::thread::spawn(move || serve(stream, &state));
+            }
+        });
+
+        Self { url, dir, state }
+    }
+
+    fn remove(&self, uid: &str) {
+        let path = format!("{}{}.ics", CALENDAR, uid);
+        let mut state = self.state.lock().unwrap();
+        state.objects.remove(&path);
+        state.removed.push(path);
+    }
+
+    fn reports(&self) -> Vec<String> {
+        self.state.lock().unwrap().reports.clone()
+    }
+
+    // Runs calcal with the server as its only source, through the config file, and returns
+    // what it printed.
+    fn calcal(&self, config: &str, args: &[&str]) -> String {
+        let config_dir = self.dir.join("config/calcal");
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::write(
+            config_dir.join("config.toml"),
+            format!("[[source]]\ncaldav = \"{}\"\n{}", self.url, config),
+        )
+        .unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_calcal"))
+            .args(args)
+            .env("TZ", "UTC")
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("XDG_CACHE_HOME", self.dir.join("cache"))
+            .output()
+            .unwrap();
+
+        assert!(
+            output.status.success(),
+            "calcal {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr),
+        );
+
+        String::from_utf8(output.stdout).unwrap()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn event(uid: &str, title: &str, starts: chrono::DateTime<chrono::Utc>) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:{}\r\nDTSTART:{}\r\n\
+         DURATION:PT1H\r\nSUMMARY:{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+        uid,
+        starts.format("%Y%m%dT%H%M%SZ"),
+        title,
+    )
+}
+
+fn serve(stream: TcpStream, state: &Mutex<State>) {
+    let mut reader = BufReader::new(&stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let path = parts.next().unwrap_or_default().to_owned();
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse().unwrap();
+        }
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    let body = String::from_utf8(body).unwrap();
+
+    let response = match respond(&method, &path, &body, &mut state.lock().unwrap()) {
+        Some(xml) => format!(
+            "HTTP/1.1 207 Multi-Status\r\nContent-Type: application/xml\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            xml.len(),
+            xml,
+        ),
+        None => {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned()
+        }
+    };
+
+    (&stream).write_all(response.as_bytes()).unwrap();
+}
+
+// Answers the requests calcal makes, the hrefs are relative where the servers allow it.
+fn respond(method: &str, path: &str, body: &str, state: &mut State) -> Option<String> {
+    let responses = match (method, path) {
+        ("PROPFIND", "/dav/") => vec![response(
+            "/dav/",
+            "<d:current-user-principal><d:href>principals/me/</d:href></d:current-user-principal>",
+        )],
+        ("PROPFIND", "/dav/principals/me/") => vec![response(
+            "/dav/principals/me/",
+            "<c:calendar-home-set><d:href>../../calendars/me/</d:href></c:calendar-home-set>",
+        )],
+        ("PROPFIND", "/dav/calendars/me/") => vec![
+            response(
+                "/dav/calendars/me/",
+                "<d:resourcetype><d:collection/></d:resourcetype>",
+            ),
+            response(
+                "work/",
+                "<d:resourcetype><d:collection/><c:calendar/></d:resourcetype>\
+                 <d:displayname>Work</d:displayname>\
+                 <c:supported-calendar-component-set><c:comp name=\"VEVENT\"/></c:supported-calendar-component-set>",
+            ),
+        ],
+        ("REPORT", CALENDAR) => {
+            state.reports.push(body.to_owned());
+            return Some(report(body, state));
+        }
+        _ => return None,
+    };
+
+    Some(multistatus(&responses.concat(), None))
+}
+
+fn report(body: &str, state: &mut State) -> String {
+    let data = |object: &str| format!("<c:calendar-data>{}</c:calendar-data>", escape(object));
+
+    if body.contains("sync-collection") {
+        // The first sync lists every object without its data, so that they are fetched
+        // separately, the later ones only list the removed objects.
+        let responses: String = match body.contains("<d:sync-token></d:sync-token>") {
+            true => state
+                .objects
+                .keys()
+                .map(|path| response(path, "<d:getetag>\"1\"</d:getetag>"))
+                .collect(),
+            false => std::mem::take(&mut state.removed)
+                .iter()
+                .map(|path| {
+                    format!(
+                        "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+                        path
+                    )
+                })
+                .collect(),
+        };
+
+        return multistatus(&responses, Some("http://dav.example.com/sync/1"));
+    }
+
+    let responses: String = state
+        .objects
+        .iter()
+        .filter(|(path, _)| !body.contains("calendar-multiget") || body.contains(path.as_str()))
+        .map(|(path, object)| response(path, &data(object)))
+        .collect();
+
+    multistatus(&responses, None)
+}
+
+fn response(href: &str, props: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:propstat><d:prop>{}</d:prop>\
+         <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+        href, props
+    )
+}
+
+fn multistatus(responses: &str, sync_token: Option<&str>) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <d:multistatus xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\">{}{}</d:multistatus>",
+        responses,
+        sync_token
+            .map(|token| format!("<d:sync-token>{}</d:sync-token>", token))
+            .unwrap_or_default(),
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[test]
+fn search_finds_the_events_on_the_discovered_calendars() {
+    let now = chrono::Utc::now();
+    let server = Server::start(&[
+        ("planning", "Planning", now + chrono::TimeDelta::days(1)),
+        ("retro", "Planning Retro", now + chrono::TimeDelta::days(2)),
+    ]);
+
+    let output = server.calcal("", &["search", "Planning"]);
+    assert!(output.contains("• Planning @ "), "{}", output);
+    assert!(output.contains("• Planning Retro @ "), "{}", output);
+
+    let reports = server.reports();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].contains("calendar-query"), "{}", reports[0]);
+    assert!(reports[0].contains("<c:time-range "), "{}", reports[0]);
+}
+
+#[test]
+fn sync_only_fetches_the_changes_since_the_last_run() {
+    let now = chrono::Utc::now();
+    let server = Server::start(&[
+        ("planning", "Planning", now + chrono::TimeDelta::days(1)),
+        ("retro", "Planning Retro", now + chrono::TimeDelta::days(2)),
+    ]);
+
+    let output = server.calcal("sync = true\n", &["search", "Planning"]);
+    assert!(output.contains("• Planning @ "), "{}", output);
+    assert!(output.contains("• Planning Retro @ "), "{}", output);
+
+    server.remove("retro");
+
+    let output = server.calcal("sync = true\n", &["search", "Planning"]);
+    assert!(output.contains("• Planning @ "), "{}", output);
+    assert!(!output.contains("Planning Retro"), "{}", output);
+
+    let reports = server.reports();
+    assert_eq!(reports.len(), 3, "{:#?}", reports);
+    assert!(reports[0].contains("<d:sync-token></d:sync-token>"));
+    assert!(reports[1].contains("calendar-multiget"));
+    assert!(reports[2].contains("<d:sync-token>http://dav.example.com/sync/1</d:sync-token>"));
+}
@@ -0,0 +1,262 @@
+mod mock;
+
+#[test]
+fn summary_shows_the_ongoing_event() {
+    let eds = mock::Eds::start();
+
+    let summary = eds.calcal(&["summary"]);
+    assert!(summary.starts_with("Standup ends in "), "{}", summary);
+}
+
+#[test]
+fn summary_shows_the_upcoming_event_on_the_selected_calendar() {
+    let eds = mock::Eds::start();
+
+    let summary = eds.calcal(&["summary", "--calendars", "Personal"]);
+    assert!(summary.starts_with("Lunch with Sam in "), "{}", summary);
+}
+
+#[test]
+fn summary_counts_the_all_day_events_when_asked() {
+    let eds = mock::Eds::start();
+
+    let summary = eds.calcal(&["summary", "--calendars", "Personal"]);
+    assert!(summary.starts_with("Lunch with Sam in "), "{}", summary);
+
+    let summary = eds.calcal(&["summary", "--calendars", "Personal", "--with-all-day"]);
+    assert!(
+        summary.starts_with("Public Holiday ends in "),
+        "{}",
+        summary
+    );
+}
+
+#[test]
+fn today_lists_the_events_on_the_visible_calendars() {
+    let eds = mock::Eds::start();
+
+    let today = eds.calcal(&["today"]);
+    let lines: Vec<_> = today.lines().collect();
+
+    assert_eq!(lines.len(), 6, "{}", today);
+    assert_eq!(lines[0], "Today");
+    assert_eq!(lines[1], "All day: Public Holiday");
+    assert!(lines[2].starts_with("• Standup @ "), "{}", today);
+    assert!(lines[3].starts_with("• Design Review @ "), "{}", today);
+    assert!(lines[4].starts_with("• Lunch with Sam @ "), "{}", today);
+    assert!(lines[5].starts_with("• Roadmap @ "), "{}", today);
+}
+
+#[test]
+fn today_hides_the_events_you_declined() {
+    let eds = mock::Eds::start();
+
+    let today = eds.calcal(&["today"]);
+    assert!(!today.contains("Budget Sync"), "{}", today);
+    assert!(today.contains("Lunch with Sam"), "{}", today);
+    assert!(today.contains("Roadmap"), "{}", today);
+
+    let today = eds.calcal(&["today", "--hide-tentative", "--hide-unanswered"]);
+    assert!(!today.contains("Lunch with Sam"), "{}", today);
+    assert!(!today.contains("Roadmap"), "{}", today);
+    assert!(today.contains("Design Review"), "{}", today);
+}
+
+#[test]
+fn today_finds_you_by_the_addresses_in_the_config() {
+    let eds = mock::Eds::start();
+
+    let args = ["today", "--include-hidden", "--calendars", "Archive"];
+
+    let today = eds.calcal(&args);
+    assert!(today.contains("• Vendor Call @ "), "{}", today);
+
+    eds.configure("addresses = [\"me@home.example\"]\n");
+    let today = eds.calcal(&args);
+    assert!(!today.contains("Vendor Call"), "{}", today);
+    assert!(today.contains("• Archived Sync @ "), "{}", today);
+}
+
+#[test]
+fn today_prints_the_details_of_the_events_as_json() {
+    let eds = mock::Eds::start();
+
+    let output = eds.calcal(&["today", "--json"]);
+    let events: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let titles: Vec<_> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        titles,
+        [
+            "Public Holiday",
+            "Standup",
+            "Design Review",
+            "Lunch with Sam",
+            "Roadmap"
+        ]
+    );
+
+    let review = &events[2];
+    assert_eq!(review["location"], "Room 4");
+    assert_eq!(review["url"], "https://meet.example.com/review");
+    assert_eq!(review["organizer"]["name"], "Alex");
+    assert_eq!(review["organizer"]["address"], "mailto:alex@example.com");
+    assert_eq!(review["attendees"][0]["name"], "Sam");
+    assert_eq!(review["attendees"][0]["role"], "REQ-PARTICIPANT");
+    assert_eq!(review["attendees"][0]["status"], "ACCEPTED");
+    assert_eq!(review["attendees"][1]["status"], "TENTATIVE");
+    assert_eq!(review["categories"], serde_json::json!(["Work", "Design"]));
+    assert_eq!(review["class"], "PRIVATE");
+    assert_eq!(review["priority"], 1);
+    assert_eq!(review["calendar"]["uid"], "meetings");
+    assert_eq!(review["calendar"]["name"], "Meetings");
+
+    assert_eq!(events[0]["all_day"], true);
+    assert_eq!(events[0]["transparent"], true);
+}
+
+#[test]
+fn today_ends_the_events_without_an_end_by_their_duration() {
+    let eds = mock::Eds::start();
+
+    let output = eds.calcal(&[
+        "today",
+        "--json",
+        "--include-hidden",
+        "--calendars",
+        "Archive",
+    ]);
+    let events: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let length = |title: &str| {
+        let event = events
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|event| event["title"] == title)
+            .unwrap_or_else(|| panic!("{} is missing: {}", title, output));
+
+        let time =
+            |key: &str| chrono::DateTime::parse_from_rfc3339(event[key].as_str().unwrap()).unwrap();
+        time("ends") - time("starts")
+    };
+
+    // An event on a date takes up that day, without a DTEND or a DURATION.
+    assert_eq!(length("Offsite"), chrono::TimeDelta::days(1));
+    assert_eq!(length("Retro"), chrono::TimeDelta::minutes(45));
+    assert_eq!(length("Archived Sync"), chrono::TimeDelta::hours(1));
+
+    let today = eds.calcal(&["today", "--include-hidden", "--calendars", "Archive"]);
+    assert!(today.contains("All day: Offsite"), "{}", today);
+}
+
+#[test]
+fn today_picks_the_events_by_their_status() {
+    let eds = mock::Eds::start();
+
+    let today = eds.calcal(&["today", "--calendars", "Personal"]);
+    assert!(!today.contains("Dentist"), "{}", today);
+    assert!(today.contains("Lunch with Sam"), "{}", today);
+
+    let today = eds.calcal(&["today", "--calendars", "Personal", "--status", "cancelled"]);
+    assert!(today.contains("• Dentist @ "), "{}", today);
+    assert!(!today.contains("Lunch with Sam"), "{}", today);
+
+    let today = eds.calcal(&[
+        "today",
+        "--calendars",
+        "Personal",
+        "--status",
+        "confirmed,cancelled",
+        "--exclude-status",
+        "confirmed",
+    ]);
+    assert!(today.contains("• Dentist @ "), "{}", today);
+    assert!(!today.contains("Lunch with Sam"), "{}", today);
+}
+
+#[test]
+fn today_includes_the_hidden_calendars_when_asked() {
+    let eds = mock::Eds::start();
+
+    let today = eds.calcal(&["today", "--include-hidden", "--calendars", "Archive"]);
+    assert!(today.contains("• Archived Sync @ "), "{}", today);
+}
+
+#[test]
+fn today_picks_the_calendars_by_their_account() {
+    let eds = mock::Eds::start();
+
+    let today = eds.calcal(&["today", "--calendars", "Work/*"]);
+    assert!(today.contains("Standup"), "{}", today);
+    assert!(!today.contains("Lunch with Sam"), "{}", today);
+}
+
+#[test]
+fn tasks_lists_the_done_and_cancelled_tasks_with_all() {
+    let eds = mock::Eds::start();
+
+    let tasks = eds.calcal(&["tasks"]);
+    assert!(tasks.contains("• Buy Groceries, due "), "{}", tasks);
+    assert!(!tasks.contains("File Taxes"), "{}", tasks);
+    assert!(!tasks.contains("Renew Gym Pass"), "{}", tasks);
+
+    let tasks = eds.calcal(&["tasks", "--all"]);
+    assert!(tasks.contains("• Buy Groceries, due "), "{}", tasks);
+    assert!(tasks.contains("• File Taxes (done)"), "{}", tasks);
+    assert!(tasks.contains("• Renew Gym Pass (done)"), "{}", tasks);
+}
+
+#[test]
+fn waybar_prints_the_summary_and_the_events_of_the_day() {
+    let eds = mock::Eds::start();
+
+    let output = eds.calcal(&["waybar"]);
+    let waybar: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let text = waybar["text"].as_str().unwrap();
+    assert!(text.starts_with("Standup ends in "), "{}", output);
+
+    let tooltip = waybar["tooltip"].as_str().unwrap();
+    assert!(
+        tooltip.starts_with("Today\nAll day: Public Holiday\n• Standup @ "),
+        "{}",
+        output
+    );
+    assert!(waybar.get("class").is_none(), "{}", output);
+}
+
+#[test]
+fn calendars_lists_the_sources_with_their_accounts() {
+    let eds = mock::Eds::start();
+
+    let output = eds.calcal(&["calendars", "--json", "--include-hidden"]);
+    let calendars: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let meetings = calendars
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|calendar| calendar["uid"] == "meetings")
+        .unwrap();
+
+    assert_eq!(meetings["name"], "Meetings");
+    assert_eq!(meetings["account"], "Work");
+    assert_eq!(meetings["backend"], "caldav");
+    assert_eq!(meetings["writable"], false);
+}
+
+#[test]
+fn calendars_does_not_read_the_config() {
+    let eds = mock::Eds::start();
+
+    eds.configure("[[source]\n");
+    let output = eds.calcal(&["calendars", "--json"]);
+    let calendars: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert!(!calendars.as_array().unwrap().is_empty());
+}
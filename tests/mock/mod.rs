@@ -0,0 +1,334 @@
+// A fake Evolution Data Server, which serves the sources and the calendars from the
+// fixtures on a bus of its own, so that calcal can be run end to end without the real one.
+// Only "dbus-daemon" needs to be installed.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const SOURCES: &str = "org.gnome.evolution.dataserver.Sources5";
+const SOURCE_MANAGER: &str = "/org/gnome/evolution/dataserver/SourceManager";
+
+const CALENDARS: &str = "org.gnome.evolution.dataserver.Calendar8";
+const CALENDAR_FACTORY: &str = "/org/gnome/evolution/dataserver/CalendarFactory";
+
+// A session bus that only the tests are on.
+const BUS_CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:path={socket}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Eds {
+    dir: PathBuf,
+    daemon: Child,
+    address: String,
+    // The timezone calcal is run in, see "noon_offset".
+    timezone: String,
+
+    _conn: zbus::blocking::Connection,
+}
+
+impl Eds {
+    // Starts a bus, and serves the fixtures on it. Fails when "dbus-daemon" is not
+    // installed, rather than letting the tests pass without running.
+    pub fn start() -> Self {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/eds");
+
+        let dir = std::env::temp_dir().join(format!(
+            "calcal-eds-{}-{}",
+            std::process::id(),
+            INSTANCES.fetch_add(1, Ordering::SeqCst),
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let config = dir.join("bus.conf");
+        let socket = dir.join("bus");
+        std::fs::write(
+            &config,
+            BUS_CONFIG.replace("{socket}", &socket.display().to_string()),
+        )
+        .unwrap();
+
+        let daemon = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .arg("--nofork")
+            .arg("--print-address")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let mut daemon = match daemon {
+            Ok(daemon) => daemon,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let _ = std::fs::remove_dir_all(&dir);
+                panic!("dbus-daemon is not installed, the end to end tests need it to run");
+            }
+            Err(err) => panic!("Could not start dbus-daemon: {}", err),
+        };
+
+        // The address is printed once the bus is ready.
+        let mut address = String::new();
+        std::io::BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim().to_owned();
+
+        let now = chrono::Utc::now();
+        let offset = noon_offset(now);
+
+        let mut builder = zbus::blocking::connection::Builder::address(address.as_str())
+            .unwrap()
+            .name(SOURCES)
+            .unwrap()
+            .name(CALENDARS)
+            .unwrap()
+            .serve_at(SOURCE_MANAGER, zbus::fdo::ObjectManager)
+            .unwrap();
+
+        let mut paths = HashMap::new();
+        for (id, (uid, data)) in sources(&fixtures).into_iter().enumerate() {
+            builder = builder
+                .serve_at(
+                    format!("{}/Source_{}", SOURCE_MANAGER, id),
+                    Source {
+                        uid: uid.clone(),
+                        data,
+                    },
+                )
+                .unwrap();
+
+            let objects = std::fs::read_to_string(fixtures.join(format!("calendars/{}.ics", uid)))
+                .map(|ics| objects(&render(&ics, now, offset)))
+                .unwrap_or_default();
+
+            let path = format!("/org/gnome/evolution/dataserver/Calendar/{}", id);
+            builder = builder
+                .serve_at(path.clone(), Calendar { objects })
+                .unwrap();
+
+            paths.insert(uid, path);
+        }
+
+        let conn = builder
+            .serve_at(CALENDAR_FACTORY, CalendarFactory { paths })
+            .unwrap()
+            .build()
+            .unwrap();
+
+        Self {
+            dir,
+            daemon,
+            address,
+            // The sign is inverted in the POSIX format, "CAL-5" is five hours ahead of UTC.
+            timezone: format!("CAL{}", -offset),
+            _conn: conn,
+        }
+    }
+
+    // Writes the config file calcal is run with.
+    pub fn configure(&self, config: &str) {
+        let dir = self.dir.join("config/calcal");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("config.toml"), config).unwrap();
+    }
+
+    // Runs calcal on this bus and returns what it printed, failing the test if it failed.
+    pub fn calcal(&self, args: &[&str]) -> String {
+        let output = Command::new(env!("CARGO_BIN_EXE_calcal"))
+            .args(args)
+            .env("DBUS_SESSION_BUS_ADDRESS", &self.address)
+            .env("TZ", &self.timezone)
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("XDG_CACHE_HOME", self.dir.join("cache"))
+            .output()
+            .unwrap();
+
+        assert!(
+            output.status.success(),
+            "calcal {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr),
+        );
+
+        String::from_utf8(output.stdout).unwrap()
+    }
+}
+
+impl Drop for Eds {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+// The sources, as they are listed by the source registry of the EDS.
+struct Source {
+    uid: String,
+    data: String,
+}
+
+#[zbus::interface(name = "org.gnome.evolution.dataserver.Source")]
+impl Source {
+    #[zbus(property, name = "UID")]
+    fn uid(&self) -> String {
+        self.uid.clone()
+    }
+
+    #[zbus(property)]
+    fn data(&self) -> String {
+        self.data.clone()
+    }
+}
+
+// Opens the calendars, the task lists and the memo lists by the UID of their source.
+struct CalendarFactory {
+    paths: HashMap<String, String>,
+}
+
+impl CalendarFactory {
+    fn open(&self, uid: &str) -> zbus::fdo::Result<(String, String)> {
+        match self.paths.get(uid) {
+            Some(path) => Ok((path.clone(), CALENDARS.to_owned())),
+            None => Err(zbus::fdo::Error::InvalidArgs(format!(
+                "There is no source \"{}\"",
+                uid
+            ))),
+        }
+    }
+}
+
+#[zbus::interface(name = "org.gnome.evolution.dataserver.CalendarFactory")]
+impl CalendarFactory {
+    fn open_calendar(&self, uid: &str) -> zbus::fdo::Result<(String, String)> {
+        self.open(uid)
+    }
+
+    fn open_task_list(&self, uid: &str) -> zbus::fdo::Result<(String, String)> {
+        self.open(uid)
+    }
+
+    fn open_memo_list(&self, uid: &str) -> zbus::fdo::Result<(String, String)> {
+        self.open(uid)
+    }
+}
+
+struct Calendar {
+    objects: Vec<String>,
+}
+
+#[zbus::interface(name = "org.gnome.evolution.dataserver.Calendar")]
+impl Calendar {
+    #[zbus(property)]
+    fn writable(&self) -> bool {
+        false
+    }
+
+    // The queries are not run, every object is returned. The fixtures only hold the
+    // events around now, which calcal expands and filters on its own.
+    fn get_object_list(&self, _q: &str) -> Vec<String> {
+        self.objects.clone()
+    }
+}
+
+// Returns the UIDs and the key files of the sources in the fixtures.
+fn sources(fixtures: &Path) -> Vec<(String, String)> {
+    let mut files: Vec<_> = std::fs::read_dir(fixtures.join("sources"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|file| file.extension().is_some_and(|ext| ext == "source"))
+        .collect();
+    files.sort();
+
+    files
+        .into_iter()
+        .map(|file| {
+            let uid = file.file_stem().unwrap().to_string_lossy().into_owned();
+            (uid, std::fs::read_to_string(&file).unwrap())
+        })
+        .collect()
+}
+
+// Replaces the times in the fixtures, like "{now+2h}" or "{now-7d-15m}", with the UTC
+// times relative to now, and the dates, like "{today+1d}", with the local dates relative
+// to today.
+fn render(ics: &str, now: chrono::DateTime<chrono::Utc>, offset: i32) -> String {
+    let time = regex::Regex::new(r"\{(now|today)((?:[+-]\d+[a-z]+)*)\}").unwrap();
+    let term = regex::Regex::new(r"([+-])(\d+[a-z]+)").unwrap();
+
+    time.replace_all(ics, |captures: &regex::Captures| {
+        let base = match &captures[1] {
+            "now" => now,
+            _ => now + chrono::TimeDelta::hours(offset as i64),
+        };
+
+        let time = term.captures_iter(&captures[2]).fold(base, |time, term| {
+            let delta = humantime::parse_duration(&term[2]).unwrap();
+            let delta = chrono::TimeDelta::from_std(delta).unwrap();
+
+            match &term[1] {
+                "+" => time + delta,
+                _ => time - delta,
+            }
+        });
+
+        match &captures[1] {
+            "now" => time.format("%Y%m%dT%H%M%SZ").to_string(),
+            _ => time.format("%Y%m%d").to_string(),
+        }
+    })
+    .into_owned()
+}
+
+// Splits a calendar into the components in it, which is how the EDS returns them.
+fn objects(ics: &str) -> Vec<String> {
+    let mut objects = Vec::new();
+
+    let mut object = String::new();
+    let mut depth = 0;
+    for line in ics.lines() {
+        if line == "BEGIN:VCALENDAR" || line == "END:VCALENDAR" {
+            continue;
+        }
+
+        if line.starts_with("BEGIN:") {
+            depth += 1;
+        }
+
+        if depth > 0 {
+            object.push_str(line);
+            object.push_str("\r\n");
+        }
+
+        if line.starts_with("END:") {
+            depth -= 1;
+            if depth == 0 {
+                objects.push(std::mem::take(&mut object));
+            }
+        }
+    }
+
+    objects
+}
+
+// Returns the offset from UTC, in hours, of the timezone where it is around noon now.
+// calcal is run in it, so that the events in the fixtures, which are placed around now,
+// never cross into another day.
+fn noon_offset(now: chrono::DateTime<chrono::Utc>) -> i32 {
+    use chrono::Timelike;
+
+    12 - now.hour() as i32
+}